@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+/// Represents errors that can occur while exporting or suppressing audit findings
+#[derive(Error, Debug)]
+pub enum AuditError {
+    /// Error that occurs when the report or suppression file cannot be read or written
+    #[error("Audit IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Error that occurs when the report cannot be serialized
+    #[error("Audit report serialization error: {0}")]
+    Serialize(#[from] serde_json::Error),
+
+    /// Error that occurs when the suppression file cannot be parsed or serialized
+    #[error("Suppression file error: {0}")]
+    Suppressions(#[from] serde_yaml::Error),
+
+    /// Error that occurs when the pattern of an audit rule is not a valid regular expression
+    #[error("Invalid audit rule {0}")]
+    InvalidRule(String),
+}
@@ -0,0 +1,399 @@
+use super::error::AuditError;
+use super::model::{AuditIssueJson, AuditResult, Severity};
+use super::suppression::fingerprint;
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// File format of an exported audit report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// SARIF 2.1.0, accepted by code scanning dashboards
+    Sarif,
+    /// Plain JSON listing the findings of each file
+    Json,
+}
+
+impl ExportFormat {
+    /// Guesses the format from the extension of the report path
+    ///
+    /// # Arguments
+    /// * `path` - Path of the report
+    ///
+    /// # Returns
+    /// SARIF for `.sarif` files, JSON otherwise
+    pub fn from_path(path: &Path) -> Self {
+        let name = path.to_string_lossy().to_lowercase();
+        if name.ends_with(".sarif") || name.ends_with(".sarif.json") {
+            ExportFormat::Sarif
+        } else {
+            ExportFormat::Json
+        }
+    }
+}
+
+/// Findings of a single file, as written in JSON reports
+#[derive(Serialize)]
+struct FileReport<'a> {
+    /// Path of the file, null for findings concerning several files
+    file: Option<&'a str>,
+    /// Issues found in the file
+    issues: Vec<&'a AuditIssueJson>,
+}
+
+/// Audit results collected over a run, exported once it completes
+#[derive(Default)]
+pub struct AuditReport {
+    /// Results of the audits, in the order they were reviewed
+    audits: Vec<AuditResult>,
+}
+
+impl AuditReport {
+    /// Creates an empty report
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the results of an audit, of a single file or of several
+    ///
+    /// # Arguments
+    /// * `result` - Results of the audit
+    pub fn add(&mut self, result: AuditResult) {
+        self.audits.push(result);
+    }
+
+    /// Writes the report to a file
+    ///
+    /// # Arguments
+    /// * `path` - Path of the report
+    /// * `format` - Format of the report
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the report was written
+    /// * `Err(AuditError)` - If the report cannot be serialized or written
+    pub fn write(&self, path: &Path, format: ExportFormat) -> Result<(), AuditError> {
+        let report = match format {
+            ExportFormat::Sarif => self.to_sarif(),
+            ExportFormat::Json => self.to_json(),
+        };
+        std::fs::write(path, serde_json::to_string_pretty(&report)?)?;
+        Ok(())
+    }
+
+    /// Builds the plain JSON report, with the findings grouped by file
+    ///
+    /// The problems met while parsing the responses, such as truncated
+    /// responses or malformed issues, are listed under `errors`.
+    fn to_json(&self) -> serde_json::Value {
+        let mut files: Vec<FileReport> = Vec::new();
+        for issue in self.audits.iter().flat_map(|audit| &audit.issues) {
+            let file = issue.file.as_deref();
+            match files.iter_mut().find(|report| report.file == file) {
+                Some(report) => report.issues.push(issue),
+                None => files.push(FileReport {
+                    file,
+                    issues: vec![issue],
+                }),
+            }
+        }
+
+        let summaries: Vec<_> = self.audits.iter().map(|audit| &audit.summary).collect();
+        let errors: Vec<_> = self.audits.iter().flat_map(|audit| &audit.errors).collect();
+        let suppressed: usize = self.audits.iter().map(|audit| audit.suppressed).sum();
+        json!({
+            "summaries": summaries,
+            "errors": errors,
+            "suppressed": suppressed,
+            "files": files
+        })
+    }
+
+    /// Builds a SARIF 2.1.0 log with one run
+    ///
+    /// Each distinct issue title becomes a rule, and every issue a result
+    /// located in its file when it concerns one, with a line region when it
+    /// is known. Results carry the suppression fingerprint so dashboards can
+    /// track them across runs.
+    fn to_sarif(&self) -> serde_json::Value {
+        let mut rules = BTreeMap::new();
+        let mut results = Vec::new();
+
+        for audit in &self.audits {
+            for issue in &audit.issues {
+                let rule_id = rule_id(&issue.title);
+                rules.entry(rule_id.clone()).or_insert_with(|| {
+                    json!({
+                        "id": rule_id,
+                        "name": issue.title,
+                        "shortDescription": { "text": issue.title },
+                        "properties": {
+                            "security-severity": security_severity(issue.severity)
+                        }
+                    })
+                });
+
+                let locations: Vec<_> = issue
+                    .file
+                    .iter()
+                    .map(|file| {
+                        let mut location = json!({
+                            "physicalLocation": {
+                                "artifactLocation": { "uri": file }
+                            }
+                        });
+                        if let (Some(start), Some(end)) = (issue.line_start, issue.line_end) {
+                            location["physicalLocation"]["region"] =
+                                json!({ "startLine": start, "endLine": end });
+                        }
+                        location
+                    })
+                    .collect();
+
+                results.push(json!({
+                    "ruleId": rule_id,
+                    "level": sarif_level(issue.severity),
+                    "message": { "text": format!("{}: {}", issue.title, issue.impact) },
+                    "locations": locations,
+                    "partialFingerprints": { "comgen/v1": fingerprint(issue) },
+                    "properties": {
+                        "severity": issue.severity,
+                        "source": issue.source,
+                        "category": issue.category,
+                        "context": issue.context,
+                        "suggestion": issue.suggestion
+                    }
+                }));
+            }
+        }
+
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "comgen",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules.into_values().collect::<Vec<_>>()
+                    }
+                },
+                "results": results
+            }]
+        })
+    }
+}
+
+/// Rule identifier of findings whose title has no words, as SARIF requires one
+const FALLBACK_RULE_ID: &str = "comgen-finding";
+
+/// Derives a stable rule identifier from an issue title
+///
+/// # Example
+/// "Hardcoded API key" becomes "hardcoded-api-key"
+fn rule_id(title: &str) -> String {
+    let id = title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-");
+    if id.is_empty() {
+        FALLBACK_RULE_ID.to_string()
+    } else {
+        id
+    }
+}
+
+/// Maps a severity to a SARIF result level
+fn sarif_level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical | Severity::High => "error",
+        Severity::Medium => "warning",
+        Severity::Low => "note",
+    }
+}
+
+/// Maps a severity to the score used by code scanning dashboards to rank rules
+fn security_severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical => "9.5",
+        Severity::High => "7.5",
+        Severity::Medium => "5.0",
+        Severity::Low => "2.0",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::{audit, issue};
+    use super::*;
+
+    /// Report of a file audit with a located finding and a truncated response,
+    /// and of a combined audit with a finding spanning files
+    fn report() -> AuditReport {
+        let mut report = AuditReport::new();
+
+        let mut file_audit = audit(vec![issue(json!({
+            "context": "let key = \"sk-1234\";",
+            "file": "src/net.rs",
+            "line_start": 2,
+            "line_end": 3,
+            "category": "security",
+        }))]);
+        file_audit.summary = "One issue".to_string();
+        file_audit.errors = vec!["Response was truncated".to_string()];
+        file_audit.suppressed = 1;
+        report.add(file_audit);
+
+        let mut combined = audit(vec![issue(json!({
+            "severity": "LOW",
+            "title": "?!",
+            "impact": "Confuses readers",
+            "suggestion": "Rename it",
+        }))]);
+        combined.summary = "Nothing serious".to_string();
+        report.add(combined);
+        report
+    }
+
+    #[test]
+    fn json_report_groups_findings_and_keeps_errors() {
+        assert_eq!(
+            report().to_json(),
+            json!({
+                "summaries": ["One issue", "Nothing serious"],
+                "errors": ["Response was truncated"],
+                "suppressed": 1,
+                "files": [
+                    {
+                        "file": "src/net.rs",
+                        "issues": [{
+                            "severity": "HIGH",
+                            "title": "Hardcoded key",
+                            "impact": "Leaks the key",
+                            "context": "let key = \"sk-1234\";",
+                            "suggestion": "Load the key from the environment",
+                            "file": "src/net.rs",
+                            "line_start": 2,
+                            "line_end": 3,
+                            "category": "security",
+                            "source": "llm",
+                        }],
+                    },
+                    {
+                        "file": null,
+                        "issues": [{
+                            "severity": "LOW",
+                            "title": "?!",
+                            "impact": "Confuses readers",
+                            "context": "",
+                            "suggestion": "Rename it",
+                            "source": "llm",
+                        }],
+                    },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn sarif_report_lists_rules_and_located_results() {
+        let report = report();
+        let located = &report.audits[0].issues[0];
+        let spanning = &report.audits[1].issues[0];
+
+        assert_eq!(
+            report.to_sarif(),
+            json!({
+                "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+                "version": "2.1.0",
+                "runs": [{
+                    "tool": {
+                        "driver": {
+                            "name": "comgen",
+                            "version": env!("CARGO_PKG_VERSION"),
+                            "rules": [
+                                {
+                                    "id": "comgen-finding",
+                                    "name": "?!",
+                                    "shortDescription": { "text": "?!" },
+                                    "properties": { "security-severity": "2.0" },
+                                },
+                                {
+                                    "id": "hardcoded-key",
+                                    "name": "Hardcoded key",
+                                    "shortDescription": { "text": "Hardcoded key" },
+                                    "properties": { "security-severity": "7.5" },
+                                },
+                            ],
+                        }
+                    },
+                    "results": [
+                        {
+                            "ruleId": "hardcoded-key",
+                            "level": "error",
+                            "message": { "text": "Hardcoded key: Leaks the key" },
+                            "locations": [{
+                                "physicalLocation": {
+                                    "artifactLocation": { "uri": "src/net.rs" },
+                                    "region": { "startLine": 2, "endLine": 3 },
+                                }
+                            }],
+                            "partialFingerprints": { "comgen/v1": fingerprint(located) },
+                            "properties": {
+                                "severity": "HIGH",
+                                "source": "llm",
+                                "category": "security",
+                                "context": "let key = \"sk-1234\";",
+                                "suggestion": "Load the key from the environment",
+                            },
+                        },
+                        {
+                            "ruleId": "comgen-finding",
+                            "level": "note",
+                            "message": { "text": "?!: Confuses readers" },
+                            "locations": [],
+                            "partialFingerprints": { "comgen/v1": fingerprint(spanning) },
+                            "properties": {
+                                "severity": "LOW",
+                                "source": "llm",
+                                "category": null,
+                                "context": "",
+                                "suggestion": "Rename it",
+                            },
+                        },
+                    ],
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn format_follows_the_extension() {
+        assert_eq!(
+            ExportFormat::from_path(Path::new("audit.SARIF")),
+            ExportFormat::Sarif
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("out/audit.sarif.json")),
+            ExportFormat::Sarif
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("audit.json")),
+            ExportFormat::Json
+        );
+    }
+
+    #[test]
+    fn rule_id_slugs_the_title() {
+        assert_eq!(rule_id("Hardcoded API key"), "hardcoded-api-key");
+        assert_eq!(
+            rule_id("  SQL: injection (format!) "),
+            "sql-injection-format"
+        );
+        assert_eq!(rule_id(""), FALLBACK_RULE_ID);
+        assert_eq!(rule_id("?!"), FALLBACK_RULE_ID);
+    }
+}
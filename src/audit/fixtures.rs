@@ -0,0 +1,30 @@
+use super::model::{AuditIssueJson, AuditResult};
+use serde_json::Value;
+
+/// Builds an issue as a model would report it
+///
+/// # Arguments
+/// * `fields` - JSON object overriding the fields of a default issue
+pub fn issue(fields: Value) -> AuditIssueJson {
+    let mut issue = serde_json::json!({
+        "severity": "HIGH",
+        "title": "Hardcoded key",
+        "impact": "Leaks the key",
+        "context": "",
+        "suggestion": "Load the key from the environment",
+    });
+    if let (Some(issue), Value::Object(fields)) = (issue.as_object_mut(), fields) {
+        issue.extend(fields);
+    }
+    serde_json::from_value(issue).unwrap()
+}
+
+/// Builds the result of an audit that found the given issues
+pub fn audit(issues: Vec<AuditIssueJson>) -> AuditResult {
+    AuditResult {
+        issues,
+        summary: String::new(),
+        errors: Vec::new(),
+        suppressed: 0,
+    }
+}
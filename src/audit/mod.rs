@@ -1,2 +1,10 @@
+pub mod error;
+pub mod export;
+#[cfg(test)]
+mod fixtures;
 pub mod model;
 pub mod parser;
+pub mod record;
+pub mod rules;
+pub mod service;
+pub mod suppression;
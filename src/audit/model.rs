@@ -1,30 +1,466 @@
-use serde::{Deserialize, Serialize};
+use crate::git::{DiffHunk, DiffLine, FileDiff, LineKind};
+use crate::providers::OutputSchema;
+use schemars::gen::SchemaSettings;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use tracing::info;
+
+/// Lines shown before and after the lines of an issue in its excerpt
+const EXCERPT_CONTEXT: u32 = 2;
+
+/// Severity of an audit finding, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, JsonSchema)]
+#[serde(rename_all = "UPPERCASE")]
+#[schemars(description = "Severity of the issue")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    /// Parses a severity name, ignoring case and surrounding whitespace
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_uppercase().as_str() {
+            "LOW" => Ok(Severity::Low),
+            "MEDIUM" => Ok(Severity::Medium),
+            "HIGH" => Ok(Severity::High),
+            "CRITICAL" => Ok(Severity::Critical),
+            _ => Err(format!(
+                "Unknown severity '{}', expected LOW, MEDIUM, HIGH or CRITICAL",
+                value
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Severity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Low => "LOW",
+            Severity::Medium => "MEDIUM",
+            Severity::High => "HIGH",
+            Severity::Critical => "CRITICAL",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Origin of an audit finding
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FindingSource {
+    /// Reported by the model
+    #[default]
+    Llm,
+    /// Reported by a local rule
+    Rule,
+}
+
+impl fmt::Display for FindingSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FindingSource::Llm => "llm",
+            FindingSource::Rule => "rule",
+        };
+        f.write_str(name)
+    }
+}
 
 /// Contains the results of a code audit
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct AuditResult {
     /// List of issues found during the audit
     pub issues: Vec<AuditIssueJson>,
     /// Overall summary of the audit findings
     pub summary: String,
+    /// Problems met while parsing the response, such as malformed issues
+    pub errors: Vec<String>,
+    /// Number of findings hidden by the suppression file
+    pub suppressed: usize,
 }
 
 impl AuditResult {
-    /// Checks if there are any critical severity issues
+    /// Attributes the issues to the audited files and validates their line ranges
+    ///
+    /// Issues naming a file that was not audited, or no file, are attributed
+    /// to the file whose diff contains the code quoted in their context, or
+    /// to the only audited file. Ranges returned by the model are kept only
+    /// when they fall inside the hunks of the file's diff. Issues without a
+    /// valid range are located from their context, and every located issue
+    /// gets an excerpt of the surrounding lines.
+    ///
+    /// # Arguments
+    /// * `diffs` - The diffs of the audited files
+    pub fn resolve_lines(&mut self, diffs: &[FileDiff]) {
+        for issue in &mut self.issues {
+            let named = issue
+                .file
+                .as_deref()
+                .and_then(|file| diffs.iter().find(|diff| same_path(&diff.file, file)));
+            let diff = match named {
+                Some(diff) => Some(diff),
+                None if diffs.len() == 1 => diffs.first(),
+                None => diffs
+                    .iter()
+                    .find(|diff| issue.locate(&diff.hunks).is_some()),
+            };
+            let Some(diff) = diff else {
+                // Cross-cutting issues concern no file in particular
+                issue.file = None;
+                issue.line_start = None;
+                issue.line_end = None;
+                continue;
+            };
+            issue.file = Some(diff.file.clone());
+            let hunks = &diff.hunks;
+
+            let range = match issue.line_start {
+                Some(start) => {
+                    let end = issue.line_end.unwrap_or(start);
+                    let (start, end) = (start.min(end), start.max(end));
+                    let in_diff = |line| hunks.iter().any(|hunk| hunk.contains(line));
+                    if in_diff(start) && in_diff(end) {
+                        Some((start, end))
+                    } else {
+                        info!(
+                            "ignoring lines {}-{} of '{}', outside the diff",
+                            start, end, issue.title
+                        );
+                        issue.locate(hunks)
+                    }
+                }
+                None => issue.locate(hunks),
+            };
+
+            issue.line_start = range.map(|(start, _)| start);
+            issue.line_end = range.map(|(_, end)| end);
+            issue.excerpt = range
+                .map(|(start, end)| excerpt(hunks, start, end))
+                .unwrap_or_default();
+        }
+    }
+
+    /// Adds the issues and errors of another audit, such as another batch of files
+    ///
+    /// # Arguments
+    /// * `other` - The audit to merge into this one
+    pub fn merge(&mut self, other: AuditResult) {
+        self.issues.extend(other.issues);
+        self.errors.extend(other.errors);
+        self.suppressed += other.suppressed;
+        if self.summary.is_empty() {
+            self.summary = other.summary;
+        } else if !other.summary.is_empty() {
+            self.summary = format!("{} {}", self.summary, other.summary);
+        }
+    }
+
+    /// Returns the part of the audit concerning a file, such as its commit
+    ///
+    /// Issues concerning several files are kept, since they concern this one too.
+    ///
+    /// # Arguments
+    /// * `file` - Path of the file, as named in the audited diff
+    pub fn for_file(&self, file: &str) -> AuditResult {
+        AuditResult {
+            issues: self
+                .issues
+                .iter()
+                .filter(|issue| {
+                    issue
+                        .file
+                        .as_deref()
+                        .is_none_or(|issue_file| issue_file == file)
+                })
+                .cloned()
+                .collect(),
+            summary: self.summary.clone(),
+            errors: self.errors.clone(),
+            suppressed: self.suppressed,
+        }
+    }
+
+    /// Returns the categories of the issues, in order of first appearance
+    ///
+    /// Issues without a category are grouped under `None`.
+    ///
+    /// # Returns
+    /// Each category with the indices of its issues
+    pub fn issues_by_category(&self) -> Vec<(Option<&str>, Vec<usize>)> {
+        let mut groups: Vec<(Option<&str>, Vec<usize>)> = Vec::new();
+        for (index, issue) in self.issues.iter().enumerate() {
+            let category = issue.category.as_deref();
+            match groups.iter_mut().find(|(group, _)| *group == category) {
+                Some((_, indices)) => indices.push(index),
+                None => groups.push((category, vec![index])),
+            }
+        }
+        groups
+    }
+
+    /// Checks if any issue reaches the gate of its category
+    ///
+    /// # Arguments
+    /// * `fail_on` - Severity from which issues of a category block the commit
     ///
     /// # Returns
-    /// `true` if there are any critical issues, `false` otherwise
-    pub fn has_critical_issues(&self) -> bool {
-        self.issues.iter().any(|issue| issue.severity == "CRITICAL")
+    /// `true` if there are any blocking issues, `false` otherwise
+    pub fn has_blocking_issues(&self, fail_on: impl Fn(Option<&str>) -> Severity) -> bool {
+        self.issues
+            .iter()
+            .any(|issue| issue.severity >= fail_on(issue.category.as_deref()))
     }
 }
 
 /// Represents a single issue found during code audit in JSON format
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields, description = "A single issue found in the diff")]
 pub struct AuditIssueJson {
-    pub severity: String,
+    /// Severity of the issue
+    pub severity: Severity,
+    /// Brief issue title
     pub title: String,
+    /// Specific consequences of the issue
     pub impact: String,
+    /// Relevant code location or pattern
     pub context: String,
+    /// Concrete steps to resolve the issue
     pub suggestion: String,
+    /// Path of the file the issue concerns, as named in the diff, null if it concerns several files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// First line of the issue in the new version of the file, null if it concerns no specific line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_start: Option<u32>,
+    /// Last line of the issue in the new version of the file, null if it concerns no specific line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_end: Option<u32>,
+    /// Category of the issue, such as security, performance or correctness
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Lines of the new version of the file around the issue, for display
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub excerpt: Vec<DiffLine>,
+    /// Whether the issue was reported by the model or a local rule
+    #[serde(skip_deserializing)]
+    #[schemars(skip)]
+    pub source: FindingSource,
+}
+
+impl AuditIssueJson {
+    /// Finds the lines of the diff quoted in the issue's context
+    ///
+    /// The first non-blank line of the context is looked up among the lines
+    /// present in the new version of the file; following context lines extend
+    /// the range as long as they match consecutive diff lines.
+    ///
+    /// # Arguments
+    /// * `hunks` - The hunks of the audited diff
+    ///
+    /// # Returns
+    /// The first and last line of the match, if the context quotes the diff
+    fn locate(&self, hunks: &[DiffHunk]) -> Option<(u32, u32)> {
+        let mut quoted = self
+            .context
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty());
+        let first = quoted.next()?;
+        // Very short contexts would match unrelated lines
+        if first.len() < 4 {
+            return None;
+        }
+
+        for hunk in hunks {
+            let lines: Vec<_> = hunk
+                .lines
+                .iter()
+                .filter(|line| line.kind != LineKind::Removed)
+                .collect();
+            let Some(index) = lines.iter().position(|line| line.content.contains(first)) else {
+                continue;
+            };
+
+            let start = lines[index].new_line?;
+            let mut end = start;
+            for (line, quote) in lines[index + 1..].iter().zip(quoted.clone()) {
+                if !line.content.contains(quote) {
+                    break;
+                }
+                end = line.new_line.unwrap_or(end);
+            }
+            return Some((start, end));
+        }
+        None
+    }
+}
+
+/// Compares a path from the audited files with a path reported by the model,
+/// which may keep the `a/` or `b/` prefix of the diff headers
+fn same_path(audited: &str, reported: &str) -> bool {
+    let reported = reported.trim().trim_start_matches("./");
+    audited == reported
+        || reported
+            .strip_prefix("a/")
+            .or_else(|| reported.strip_prefix("b/"))
+            .is_some_and(|path| path == audited)
+}
+
+/// Returns the lines of the new version of the file around a range
+///
+/// # Arguments
+/// * `hunks` - The hunks of the audited diff
+/// * `start` - First line of the range
+/// * `end` - Last line of the range
+fn excerpt(hunks: &[DiffHunk], start: u32, end: u32) -> Vec<DiffLine> {
+    let first = start.saturating_sub(EXCERPT_CONTEXT);
+    let last = end + EXCERPT_CONTEXT;
+    hunks
+        .iter()
+        .flat_map(|hunk| &hunk.lines)
+        .filter(|line| line.new_line.is_some_and(|n| n >= first && n <= last))
+        .cloned()
+        .collect()
+}
+
+/// Audit response requested from providers supporting structured output
+///
+/// Structured output APIs require an object at the top level, so the issues
+/// are wrapped in an `issues` field.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[schemars(
+    deny_unknown_fields,
+    description = "Issues found while auditing the diff"
+)]
+pub struct AuditResponseJson {
+    /// Issues found in the diff, empty if there are none
+    pub issues: Vec<AuditIssueJson>,
+    /// One or two sentence overall assessment of the diff
+    pub summary: String,
+}
+
+impl AuditResponseJson {
+    /// Returns the JSON schema that structured audit responses must follow
+    ///
+    /// Subschemas are inlined since not every provider resolves references.
+    pub fn output_schema() -> OutputSchema {
+        let generator = SchemaSettings::draft07()
+            .with(|settings| {
+                settings.inline_subschemas = true;
+                settings.meta_schema = None;
+            })
+            .into_generator();
+        let schema = generator.into_root_schema_for::<AuditResponseJson>();
+        let mut schema = serde_json::to_value(schema).unwrap_or_default();
+        make_strict(&mut schema);
+
+        OutputSchema::new(
+            "audit_report",
+            "Report the issues found in the audited diff",
+            schema,
+        )
+    }
+}
+
+/// Adapts a generated schema to strict structured output modes
+///
+/// Strict modes require every property to be listed as required, optional
+/// values being expressed as nullable types instead, and reject the numeric
+/// `format` annotations added for Rust integer types.
+///
+/// # Arguments
+/// * `schema` - The schema to adapt in place
+fn make_strict(schema: &mut serde_json::Value) {
+    let Some(object) = schema.as_object_mut() else {
+        return;
+    };
+
+    if object.get("type").and_then(|t| t.as_str()) != Some("string") {
+        object.remove("format");
+    }
+    if let Some(properties) = object.get("properties").and_then(|p| p.as_object()) {
+        let required: Vec<_> = properties.keys().cloned().collect();
+        object.insert("required".to_string(), serde_json::json!(required));
+    }
+
+    for value in object.values_mut() {
+        match value {
+            serde_json::Value::Object(_) => make_strict(value),
+            serde_json::Value::Array(items) => items.iter_mut().for_each(make_strict),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::{self, audit};
+    use super::*;
+    use serde_json::json;
+
+    const DIFF: &str = "\
+@@ -1,3 +1,4 @@
+ fn connect() {
+-    let key = load();
++    let key = \"sk-1234\";
++    send(key);
+ }
+";
+
+    fn issue(context: &str, line_start: Option<u32>) -> AuditIssueJson {
+        fixtures::issue(json!({
+            "context": context,
+            "file": "b/src/net.rs",
+            "line_start": line_start,
+        }))
+    }
+
+    #[test]
+    fn resolve_lines_keeps_lines_in_the_diff() {
+        let mut result = audit(vec![issue("", Some(2))]);
+        result.resolve_lines(&[FileDiff::new("src/net.rs", DIFF)]);
+
+        let issue = &result.issues[0];
+        assert_eq!(issue.file.as_deref(), Some("src/net.rs"));
+        assert_eq!((issue.line_start, issue.line_end), (Some(2), Some(2)));
+        assert!(issue
+            .excerpt
+            .iter()
+            .any(|line| line.content.contains("sk-1234")));
+    }
+
+    #[test]
+    fn resolve_lines_locates_out_of_range_issues_from_context() {
+        let context = "let key = \"sk-1234\";\nsend(key);";
+        let mut result = audit(vec![issue(context, Some(90))]);
+        result.resolve_lines(&[FileDiff::new("src/net.rs", DIFF)]);
+
+        let issue = &result.issues[0];
+        assert_eq!((issue.line_start, issue.line_end), (Some(2), Some(3)));
+    }
+
+    #[test]
+    fn resolve_lines_drops_unlocated_lines() {
+        let mut result = audit(vec![issue("unrelated code", Some(90))]);
+        result.resolve_lines(&[FileDiff::new("src/net.rs", DIFF)]);
+
+        let issue = &result.issues[0];
+        assert_eq!((issue.line_start, issue.line_end), (None, None));
+        assert!(issue.excerpt.is_empty());
+    }
 }
@@ -1,34 +1,298 @@
 use super::model::{AuditIssueJson, AuditResult};
+use serde_json::Value;
 
 /// Parses an audit response string into an AuditResult
 ///
+/// The response does not need to be clean JSON: the first JSON array or object
+/// of the text shaped like audit issues is used, trailing commas are removed and truncated output
+/// is cut back to its last complete issue. Both a bare array of issues and an
+/// object wrapper (`{"issues": [...], "summary": "..."}`) are accepted, and
+/// issues that cannot be parsed are reported individually in `errors`.
+///
 /// # Arguments
 ///
 /// * `response` - The raw audit response string to parse
-/// * `file` - The name of the file being audited
 ///
 /// # Returns
 ///
 /// Returns a Result containing either:
-/// * An AuditResult with the parsed issues and summary
-/// * An error if parsing fails
+/// * An AuditResult with the parsed issues, summary and per-issue errors
+/// * An error if the response contains no usable JSON
 ///
 /// # Example
 ///
 /// ```
-/// let response = "CRITICAL: Security vulnerability found\nMEDIUM: Code smell detected";
-/// let result = parse_audit_response(response, "src/main.rs")?;
+/// let response = "Here is the audit:\n[{\"severity\": \"LOW\", ...},]";
+/// let result = parse_audit_response(response)?;
 /// ```
-pub fn parse_audit_response(response: &str) -> Result<AuditResult, Box<dyn std::error::Error>> {
-    let mut json_response: String;
+pub fn parse_audit_response(
+    response: &str,
+) -> Result<AuditResult, Box<dyn std::error::Error + Send + Sync>> {
+    let found = find_json(response).ok_or("No audit issues found in response")?;
 
-    json_response = response.replace("```json", "");
-    json_response = json_response.replace("```", "");
-    let issues: Vec<AuditIssueJson> = serde_json::from_str(&json_response)?;
+    let mut issues = Vec::new();
+    let mut errors = Vec::new();
+    for (index, item) in found.items.into_iter().enumerate() {
+        match serde_json::from_value::<AuditIssueJson>(item) {
+            Ok(issue) => issues.push(issue),
+            Err(e) => errors.push(format!("issue {} could not be parsed: {}", index + 1, e)),
+        }
+    }
+    if found.truncated {
+        errors.push("response was truncated, incomplete issues were dropped".to_string());
+    }
 
-    let issue_count = issues.len();
+    let summary = found
+        .summary
+        .unwrap_or_else(|| format!("Found {} issues", issues.len()));
     Ok(AuditResult {
         issues,
-        summary: format!("Found {} issues", issue_count),
+        summary,
+        errors,
+        suppressed: 0,
     })
 }
+
+/// Issues found in an audit response, before they are deserialized
+struct FoundIssues {
+    /// The issues, each parsed on its own so one malformed issue does not hide the others
+    items: Vec<Value>,
+    /// Summary given along the issues, if any
+    summary: Option<String>,
+    /// Whether the JSON had to be repaired after truncation
+    truncated: bool,
+}
+
+/// Finds the first JSON array or object of a text holding audit issues
+///
+/// Every opening bracket is tried in turn, so brackets in surrounding prose,
+/// and JSON values that are not shaped like issues, are skipped.
+fn find_json(text: &str) -> Option<FoundIssues> {
+    text.char_indices()
+        .filter(|(_, c)| *c == '[' || *c == '{')
+        .find_map(|(start, _)| {
+            let (candidate, truncated) = repair_json(&text[start..]);
+            let value = serde_json::from_str(&candidate).ok()?;
+            let (items, summary) = issue_items(value)?;
+            Some(FoundIssues {
+                items,
+                summary,
+                truncated,
+            })
+        })
+}
+
+/// Extracts the issues of a JSON value shaped like an audit response
+///
+/// Accepts an array of objects, an object wrapping the issues in `issues`
+/// with an optional `summary`, and a single issue returned without its array.
+/// Arrays of other values, such as references quoted in prose, are rejected.
+///
+/// # Returns
+/// The issues and the summary, `None` if the value holds no issues
+fn issue_items(value: Value) -> Option<(Vec<Value>, Option<String>)> {
+    match value {
+        Value::Array(items) if items.iter().all(Value::is_object) => Some((items, None)),
+        Value::Object(mut object) => match object.remove("issues") {
+            Some(Value::Array(items)) => {
+                let summary = object
+                    .get("summary")
+                    .and_then(Value::as_str)
+                    .filter(|summary| !summary.trim().is_empty())
+                    .map(str::to_string);
+                Some((items, summary))
+            }
+            None if object.contains_key("severity") => Some((vec![Value::Object(object)], None)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Extracts the JSON value starting a text, removing trailing commas
+///
+/// When the value is not terminated, it is cut back to the last closed array
+/// or object and its remaining brackets are closed.
+///
+/// # Arguments
+/// * `text` - Text starting with `[` or `{`
+///
+/// # Returns
+/// The repaired JSON text, and whether it was truncated
+fn repair_json(text: &str) -> (String, bool) {
+    let mut output = String::new();
+    // Closing brackets expected for the currently open arrays and objects
+    let mut closers = Vec::new();
+    // Output length and open brackets after the last closed array or object
+    let mut last_complete: Option<(usize, Vec<char>)> = None;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for c in text.chars() {
+        if in_string {
+            output.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match c {
+            '"' => in_string = true,
+            '[' => closers.push(']'),
+            '{' => closers.push('}'),
+            ']' | '}' => {
+                strip_trailing_comma(&mut output);
+                if closers.pop() != Some(c) {
+                    // Mismatched bracket, treat the rest as garbage
+                    break;
+                }
+                output.push(c);
+                if closers.is_empty() {
+                    return (output, false);
+                }
+                last_complete = Some((output.len(), closers.clone()));
+                continue;
+            }
+            _ => {}
+        }
+        output.push(c);
+    }
+
+    // Unterminated value: keep what was complete and close the open brackets
+    let outermost = if text.starts_with('[') { ']' } else { '}' };
+    let (length, open) = last_complete.unwrap_or_else(|| (1, vec![outermost]));
+    output.truncate(length);
+    strip_trailing_comma(&mut output);
+    output.extend(open.iter().rev());
+    (output, true)
+}
+
+/// Removes trailing whitespace and a trailing comma from the output
+fn strip_trailing_comma(output: &mut String) {
+    let trimmed = output.trim_end().len();
+    output.truncate(trimmed);
+    if output.ends_with(',') {
+        output.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ISSUE: &str = r#"{"severity": "HIGH", "title": "Hardcoded key", "impact": "Leaks the key", "context": "let key = \"sk-1\";", "suggestion": "Read it from the environment"}"#;
+
+    fn titles(result: &AuditResult) -> Vec<&str> {
+        result
+            .issues
+            .iter()
+            .map(|issue| issue.title.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn parses_fenced_responses() {
+        let response = format!(
+            "```json\n{{\"issues\": [{}], \"summary\": \"One issue\"}}\n```",
+            ISSUE
+        );
+        let result = parse_audit_response(&response).unwrap();
+        assert_eq!(titles(&result), ["Hardcoded key"]);
+        assert_eq!(result.summary, "One issue");
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn skips_prose_before_the_issues() {
+        let response = format!(
+            "I reviewed the diff [see lines 1-3] and {{notes}} below:\n[{}]",
+            ISSUE
+        );
+        let result = parse_audit_response(&response).unwrap();
+        assert_eq!(titles(&result), ["Hardcoded key"]);
+        assert_eq!(result.summary, "Found 1 issues");
+    }
+
+    #[test]
+    fn skips_json_values_that_are_not_issues() {
+        let response = format!(
+            "Checked {{\"files\": 2}} and [1, 2] first.\n{{\"issues\": [{}]}}",
+            ISSUE
+        );
+        let result = parse_audit_response(&response).unwrap();
+        assert_eq!(titles(&result), ["Hardcoded key"]);
+    }
+
+    #[test]
+    fn removes_trailing_commas() {
+        let response = format!("[{},]", ISSUE.replace("\"}", "\",}"));
+        let result = parse_audit_response(&response).unwrap();
+        assert_eq!(titles(&result), ["Hardcoded key"]);
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn keeps_complete_issues_of_truncated_responses() {
+        let response = format!("{{\"issues\": [{}, {{\"severity\": \"LOW\", \"tit", ISSUE);
+        let result = parse_audit_response(&response).unwrap();
+        assert_eq!(titles(&result), ["Hardcoded key"]);
+        assert_eq!(
+            result.errors,
+            ["response was truncated, incomplete issues were dropped"]
+        );
+    }
+
+    #[test]
+    fn reports_malformed_issues_individually() {
+        let response = format!("[{}, {{\"severity\": \"URGENT\"}}]", ISSUE);
+        let result = parse_audit_response(&response).unwrap();
+        assert_eq!(titles(&result), ["Hardcoded key"]);
+        assert_eq!(result.errors.len(), 1);
+        assert!(result.errors[0].starts_with("issue 2 could not be parsed"));
+    }
+
+    #[test]
+    fn accepts_a_single_issue_and_empty_results() {
+        let result = parse_audit_response(ISSUE).unwrap();
+        assert_eq!(titles(&result), ["Hardcoded key"]);
+        let result = parse_audit_response("No problems found: []").unwrap();
+        assert!(result.issues.is_empty());
+    }
+
+    #[test]
+    fn rejects_responses_without_issues() {
+        assert!(parse_audit_response("Looks good to me.").is_err());
+        assert!(parse_audit_response("{\"status\": \"ok\"}").is_err());
+    }
+
+    #[test]
+    fn repair_json_stops_at_the_end_of_the_value() {
+        assert_eq!(
+            repair_json("[1, [2, 3],] trailing text"),
+            ("[1, [2, 3]]".to_string(), false)
+        );
+    }
+
+    #[test]
+    fn repair_json_ignores_brackets_in_strings() {
+        let text = r#"{"a": "x]}\"", "b": [1,],}"#;
+        assert_eq!(
+            repair_json(text),
+            (r#"{"a": "x]}\"", "b": [1]}"#.to_string(), false)
+        );
+    }
+
+    #[test]
+    fn repair_json_closes_truncated_values() {
+        assert_eq!(
+            repair_json(r#"{"issues": [{"a": 1}, {"b": "#),
+            (r#"{"issues": [{"a": 1}]}"#.to_string(), true)
+        );
+        assert_eq!(repair_json("[{\"a\""), ("[]".to_string(), true));
+    }
+}
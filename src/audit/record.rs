@@ -0,0 +1,66 @@
+use super::error::AuditError;
+use super::model::{AuditResult, Severity};
+use serde_json::json;
+
+/// Notes ref holding the audit results of commits
+pub const NOTES_REF: &str = "comgen-audit";
+
+/// Builds the trailers recording the audit of a commit
+///
+/// # Arguments
+/// * `result` - Findings of the audit covering the commit
+/// * `auditor` - Model that audited the changes, or `rules` for local rules only
+///
+/// # Returns
+/// The `Audited-by` and `Audit-Findings` trailers, e.g. `Audit-Findings: 0 critical, 2 medium`
+pub fn trailers(result: &AuditResult, auditor: &str) -> Vec<String> {
+    vec![
+        format!("Audited-by: comgen/{}", auditor),
+        format!("Audit-Findings: {}", findings_count(result)),
+    ]
+}
+
+/// Builds the content of the audit note attached to a commit
+///
+/// # Arguments
+/// * `result` - Findings of the audit covering the commit
+/// * `auditor` - Model that audited the changes, or `rules` for local rules only
+///
+/// # Returns
+/// * `Ok(String)` - The full result as pretty-printed JSON
+/// * `Err(AuditError)` - If the result cannot be serialized
+pub fn note(result: &AuditResult, auditor: &str) -> Result<String, AuditError> {
+    let note = json!({
+        "audited_by": format!("comgen/{}", auditor),
+        "findings": findings_count(result),
+        "summary": result.summary,
+        "issues": result.issues,
+        "errors": result.errors,
+        "suppressed": result.suppressed
+    });
+    Ok(serde_json::to_string_pretty(&note)? + "\n")
+}
+
+/// Counts the findings by severity, critical ones always being listed
+fn findings_count(result: &AuditResult) -> String {
+    let count = |severity: Severity| {
+        result
+            .issues
+            .iter()
+            .filter(|issue| issue.severity == severity)
+            .count()
+    };
+
+    let mut counts = vec![format!("{} critical", count(Severity::Critical))];
+    for (severity, name) in [
+        (Severity::High, "high"),
+        (Severity::Medium, "medium"),
+        (Severity::Low, "low"),
+    ] {
+        let count = count(severity);
+        if count > 0 {
+            counts.push(format!("{} {}", count, name));
+        }
+    }
+    counts.join(", ")
+}
@@ -0,0 +1,195 @@
+use super::model::{AuditResponseJson, AuditResult};
+use super::parser::parse_audit_response;
+use super::rules::RuleEngine;
+use crate::config::AuditConfig;
+use crate::generator::{Generator, Task};
+use crate::git::{format_numbered, FileDiff};
+use tracing::info;
+
+/// Instructions appended to the audit prompt so findings can be mapped back to files and lines
+const LOCATION_INSTRUCTIONS: &str = "\n\nThe diff of each file follows a \"File:\" line with \
+    its path. Each diff line is prefixed with its line number in the new version of the file; \
+    removed lines have no number. For each issue, also set \"file\" to the path of the file it \
+    concerns, or null if it concerns several files, and \"line_start\" and \"line_end\" to the \
+    first and last line numbers it concerns in that file, or null if it does not concern \
+    specific lines.";
+
+/// Instructions appended to the prompt of the general profile so findings can be grouped
+const CATEGORY_INSTRUCTIONS: &str = "\n\nFor each issue, also set \"category\" to one of \
+    security, performance, correctness, test_coverage, api_compatibility or maintainability.";
+
+/// Builds the audit prompt for the diffs of one or more files
+///
+/// Diff lines are numbered and preceded by the path of their file, so the
+/// model can report the file and lines of each issue.
+///
+/// # Arguments
+/// * `profile` - Name of the audit profile
+/// * `prompt` - Prompt template of the profile
+/// * `diffs` - The diffs of the audited files
+///
+/// # Returns
+/// The prompt sent to the provider
+pub fn build_audit_prompt(profile: &str, prompt: &str, diffs: &[FileDiff]) -> String {
+    let mut audit_prompt =
+        prompt.replace("{{git_diff}}", &format_diffs(diffs)) + LOCATION_INSTRUCTIONS;
+    // Other profiles focus on a single category
+    if profile == "general" {
+        audit_prompt.push_str(CATEGORY_INSTRUCTIONS);
+    }
+    audit_prompt
+}
+
+/// Audits the diffs of one or more files, with one request per active profile
+///
+/// Findings of a profile are filed under its name and their severities are
+/// mapped as it configures. The general profile keeps the category reported
+/// by the model.
+///
+/// # Arguments
+/// * `generator` - Generator used for the audit
+/// * `config` - The audit configuration
+/// * `rules` - Local rules applied to the added lines, if they apply to this audit
+/// * `diffs` - The diffs of the audited files
+///
+/// # Returns
+/// * `Ok(AuditResult)` - The findings, attributed to the files and lines of the diffs
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If generation or parsing failed
+pub async fn audit_files(
+    generator: &Generator,
+    config: &AuditConfig,
+    rules: Option<&RuleEngine>,
+    diffs: &[FileDiff],
+) -> Result<AuditResult, Box<dyn std::error::Error + Send + Sync>> {
+    let findings: Vec<_> = rules
+        .map(|rules| diffs.iter().flat_map(|diff| rules.check(diff)).collect())
+        .unwrap_or_default();
+
+    let mut audit = AuditResult {
+        issues: Vec::new(),
+        summary: String::new(),
+        errors: Vec::new(),
+        suppressed: 0,
+    };
+    if config.llm {
+        let several = config.active_profiles.len() > 1;
+        for name in &config.active_profiles {
+            let profile = config
+                .profile(name)
+                .ok_or_else(|| format!("Unknown audit profile '{}'", name))?;
+            let audit_prompt = build_audit_prompt(name, &profile.prompt, diffs);
+            let schema = config.structured.then(AuditResponseJson::output_schema);
+            let audit_response = generator
+                .generate(Task::Audit, &audit_prompt, schema.as_ref())
+                .await?;
+
+            let mut profile_audit = parse_audit_response(&audit_response)?;
+            for issue in &mut profile_audit.issues {
+                issue.severity = profile.map_severity(issue.severity);
+                issue.category = match issue.category.as_deref().map(str::trim) {
+                    Some(category) if name == "general" && !category.is_empty() => {
+                        Some(category.to_lowercase().replace([' ', '-'], "_"))
+                    }
+                    _ => Some(name.clone()),
+                };
+            }
+            if several {
+                profile_audit.summary = format!("[{}] {}", name, profile_audit.summary);
+            }
+            audit.merge(profile_audit);
+        }
+    } else {
+        audit.summary = format!("Found {} issues with local rules", findings.len());
+    }
+
+    // Rule findings are shown and gated like the model's
+    if config.llm && !findings.is_empty() {
+        audit.summary = format!(
+            "{} ({} more from local rules)",
+            audit.summary,
+            findings.len()
+        );
+    }
+    audit.issues.extend(findings);
+    audit.resolve_lines(diffs);
+    Ok(audit)
+}
+
+/// Audits a whole change set, in as few requests as `max_diff_chars` allows
+///
+/// Files are sent together so the model can spot issues spanning several of
+/// them. Change sets larger than the budget are split between files, a file
+/// larger than the budget being sent on its own.
+///
+/// # Arguments
+/// * `generator` - Generator used for the audit
+/// * `config` - The audit configuration
+/// * `rules` - Local rules applied to the added lines, if they apply to this audit
+/// * `diffs` - The diffs of the audited files
+///
+/// # Returns
+/// * `Ok(AuditResult)` - The findings of every request
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If a request failed
+pub async fn audit_combined(
+    generator: &Generator,
+    config: &AuditConfig,
+    rules: Option<&RuleEngine>,
+    diffs: &[FileDiff],
+) -> Result<AuditResult, Box<dyn std::error::Error + Send + Sync>> {
+    let batches = batches(diffs, config.max_diff_chars);
+    if batches.len() > 1 {
+        info!(
+            "change set exceeds {} characters, auditing it in {} requests",
+            config.max_diff_chars,
+            batches.len()
+        );
+    }
+
+    let mut combined: Option<AuditResult> = None;
+    for batch in batches {
+        let audit = audit_files(generator, config, rules, batch).await?;
+        match &mut combined {
+            Some(combined) => combined.merge(audit),
+            None => combined = Some(audit),
+        }
+    }
+    Ok(combined.unwrap_or_else(|| AuditResult {
+        issues: Vec::new(),
+        summary: "No changes to audit".to_string(),
+        errors: Vec::new(),
+        suppressed: 0,
+    }))
+}
+
+/// Formats the diffs of several files, each preceded by its path
+fn format_diffs(diffs: &[FileDiff]) -> String {
+    diffs
+        .iter()
+        .map(|diff| format!("File: {}\n{}", diff.file, format_numbered(&diff.hunks)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Splits diffs into consecutive batches whose formatted size fits a budget
+///
+/// # Arguments
+/// * `diffs` - The diffs to split
+/// * `max_chars` - Maximum formatted size of a batch, exceeded only by single large files
+fn batches(diffs: &[FileDiff], max_chars: usize) -> Vec<&[FileDiff]> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut size = 0;
+    for (index, diff) in diffs.iter().enumerate() {
+        let diff_size = format_diffs(std::slice::from_ref(diff)).len();
+        if index > start && size + diff_size > max_chars {
+            batches.push(&diffs[start..index]);
+            start = index;
+            size = 0;
+        }
+        size += diff_size;
+    }
+    if start < diffs.len() {
+        batches.push(&diffs[start..]);
+    }
+    batches
+}
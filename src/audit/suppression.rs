@@ -0,0 +1,292 @@
+use super::error::AuditError;
+use super::model::{AuditIssueJson, AuditResult};
+use crate::git::GitCommands;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Suppression file, committed at the root of the repository
+pub const SUPPRESSIONS_FILE: &str = "comgen.suppressions";
+
+/// Returns the path of the suppression file of the current repository
+///
+/// Outside a git working tree, the file is looked up in the current directory.
+pub fn suppressions_path() -> PathBuf {
+    match GitCommands::toplevel() {
+        Ok(toplevel) => toplevel.join(SUPPRESSIONS_FILE),
+        Err(_) => PathBuf::from(SUPPRESSIONS_FILE),
+    }
+}
+
+/// An acknowledged audit finding, hidden from later audits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Suppression {
+    /// Fingerprint of the finding, see [`fingerprint`]
+    pub fingerprint: String,
+    /// File the finding was reported in, for readers of the file, empty if
+    /// it concerns several files
+    #[serde(default)]
+    pub file: String,
+    /// Title of the finding, for readers of the file
+    pub title: String,
+    /// Why the finding is accepted
+    pub reason: String,
+    /// Last day the suppression applies, forever if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<NaiveDate>,
+}
+
+impl Suppression {
+    /// Checks whether the suppression still applies on a given day
+    ///
+    /// # Arguments
+    /// * `today` - The current date
+    pub fn is_active(&self, today: NaiveDate) -> bool {
+        self.expires.is_none_or(|expires| today <= expires)
+    }
+}
+
+/// Content of the suppression file
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Suppressions {
+    /// Acknowledged findings, in the order they were added
+    #[serde(default)]
+    pub suppressions: Vec<Suppression>,
+}
+
+impl Suppressions {
+    /// Loads the suppression file, a missing file meaning no suppressions
+    ///
+    /// # Arguments
+    /// * `path` - Path of the suppression file
+    ///
+    /// # Returns
+    /// * `Ok(Suppressions)` - The suppressions of the file
+    /// * `Err(AuditError)` - If the file cannot be read or parsed
+    pub fn load(path: &Path) -> Result<Self, AuditError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_yaml::from_str(&content)?)
+    }
+
+    /// Writes the suppressions back to the suppression file
+    ///
+    /// # Arguments
+    /// * `path` - Path of the suppression file
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the file was written
+    /// * `Err(AuditError)` - If the file cannot be serialized or written
+    pub fn save(&self, path: &Path) -> Result<(), AuditError> {
+        std::fs::write(path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Checks whether an active suppression covers a fingerprint
+    ///
+    /// # Arguments
+    /// * `fingerprint` - Fingerprint of the finding
+    /// * `today` - The current date
+    pub fn is_suppressed(&self, fingerprint: &str, today: NaiveDate) -> bool {
+        self.suppressions
+            .iter()
+            .any(|s| s.fingerprint == fingerprint && s.is_active(today))
+    }
+
+    /// Acknowledges a finding
+    ///
+    /// Expired suppressions of the same finding are replaced.
+    ///
+    /// # Arguments
+    /// * `issue` - The finding
+    /// * `reason` - Why the finding is accepted
+    /// * `expires` - Last day the suppression applies, forever if `None`
+    pub fn add(&mut self, issue: &AuditIssueJson, reason: &str, expires: Option<NaiveDate>) {
+        let fingerprint = fingerprint(issue);
+        self.suppressions.retain(|s| s.fingerprint != fingerprint);
+        self.suppressions.push(Suppression {
+            fingerprint,
+            file: issue.file.clone().unwrap_or_default(),
+            title: issue.title.clone(),
+            reason: reason.to_string(),
+            expires,
+        });
+    }
+
+    /// Removes the suppressed findings of an audit, counting them in the result
+    ///
+    /// # Arguments
+    /// * `result` - Results of the audit, updated in place
+    /// * `today` - The current date, expired suppressions being ignored
+    pub fn apply(&self, result: &mut AuditResult, today: NaiveDate) {
+        let before = result.issues.len();
+        result.issues.retain(|issue| {
+            let suppressed = self.is_suppressed(&fingerprint(issue), today);
+            if suppressed {
+                info!(
+                    "suppressed finding '{}' in {}",
+                    issue.title,
+                    issue.file.as_deref().unwrap_or("several files")
+                );
+            }
+            !suppressed
+        });
+        result.suppressed += before - result.issues.len();
+    }
+}
+
+/// Computes the fingerprint identifying a finding across runs
+///
+/// The fingerprint hashes the file, the normalized title and a hash of the
+/// normalized context, so it survives changes in case, punctuation and
+/// whitespace but not a different file or different offending code. Line
+/// numbers are left out since they shift as the file is edited.
+///
+/// # Arguments
+/// * `issue` - The finding
+///
+/// # Returns
+/// The first 16 hex digits of the SHA-256 hash
+pub fn fingerprint(issue: &AuditIssueJson) -> String {
+    let file = issue.file.as_deref().unwrap_or_default();
+    let title = normalize(&issue.title);
+    let context = hex(&Sha256::digest(normalize(&issue.context).as_bytes()));
+
+    let mut hasher = Sha256::new();
+    for part in [file, &title, &context] {
+        // Prefix each part with its length so boundaries are unambiguous
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    hex(&hasher.finalize())[..16].to_string()
+}
+
+/// Lowercases a text and reduces it to its words separated by single spaces
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Hex-encodes a hash
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::{audit, issue};
+    use super::*;
+    use serde_json::json;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 1, day).unwrap()
+    }
+
+    #[test]
+    fn normalize_keeps_lowercase_words() {
+        assert_eq!(normalize("  Hardcoded API-key!\n"), "hardcoded api key");
+        assert_eq!(normalize("let key = \"sk-1\";"), "let key sk 1");
+        assert_eq!(normalize(" \t"), "");
+    }
+
+    #[test]
+    fn fingerprint_survives_formatting_and_moves() {
+        let original = issue(json!({
+            "file": "src/net.rs",
+            "context": "let key = \"sk-1234\";",
+            "line_start": 3,
+        }));
+        let reformatted = issue(json!({
+            "file": "src/net.rs",
+            "title": "hardcoded  KEY.",
+            "context": "let key =\n    \"sk-1234\" ;",
+            "line_start": 42,
+        }));
+        assert_eq!(fingerprint(&original), fingerprint(&reformatted));
+        assert_eq!(fingerprint(&original).len(), 16);
+    }
+
+    #[test]
+    fn fingerprint_depends_on_file_title_and_code() {
+        let original = issue(json!({"file": "src/net.rs", "context": "let key = \"sk-1234\";"}));
+        let others = [
+            issue(json!({"file": "src/db.rs", "context": "let key = \"sk-1234\";"})),
+            issue(json!({
+                "file": "src/net.rs",
+                "title": "Hardcoded token",
+                "context": "let key = \"sk-1234\";",
+            })),
+            issue(json!({"file": "src/net.rs", "context": "let key = \"sk-5678\";"})),
+            issue(json!({"context": "let key = \"sk-1234\";"})),
+        ];
+        for other in &others {
+            assert_ne!(fingerprint(&original), fingerprint(other));
+        }
+
+        // Part boundaries are unambiguous
+        let split = issue(json!({"file": "a", "title": "b c"}));
+        let moved = issue(json!({"file": "a b", "title": "c"}));
+        assert_ne!(fingerprint(&split), fingerprint(&moved));
+    }
+
+    #[test]
+    fn suppressions_expire_after_their_last_day() {
+        let finding = issue(json!({"file": "src/net.rs", "context": "key"}));
+        let mut suppressions = Suppressions::default();
+        suppressions.add(&finding, "test fixture", Some(date(10)));
+
+        let fingerprint = fingerprint(&finding);
+        assert!(suppressions.is_suppressed(&fingerprint, date(10)));
+        assert!(!suppressions.is_suppressed(&fingerprint, date(11)));
+
+        // Suppressing the finding again replaces the old entry
+        suppressions.add(&finding, "still a fixture", None);
+        assert_eq!(suppressions.suppressions.len(), 1);
+        assert_eq!(suppressions.suppressions[0].reason, "still a fixture");
+        assert!(suppressions.is_suppressed(&fingerprint, date(31)));
+    }
+
+    #[test]
+    fn apply_removes_and_counts_suppressed_findings() {
+        let accepted = issue(json!({"file": "src/net.rs", "context": "key"}));
+        let expired = issue(json!({"file": "src/db.rs", "title": "SQL injection"}));
+        let open = issue(json!({"title": "Unwrap", "context": "unwrap"}));
+
+        let mut suppressions = Suppressions::default();
+        suppressions.add(&accepted, "test fixture", None);
+        suppressions.add(&expired, "fixed soon", Some(date(1)));
+
+        let mut result = audit(vec![accepted, expired, open]);
+        suppressions.apply(&mut result, date(2));
+
+        let titles: Vec<_> = result.issues.iter().map(|i| i.title.as_str()).collect();
+        assert_eq!(titles, ["SQL injection", "Unwrap"]);
+        assert_eq!(result.suppressed, 1);
+    }
+
+    #[test]
+    fn suppressions_round_trip_through_yaml() {
+        let mut suppressions = Suppressions::default();
+        let finding = issue(json!({"file": "src/net.rs", "context": "key"}));
+        suppressions.add(&finding, "fixture", None);
+
+        let yaml = serde_yaml::to_string(&suppressions).unwrap();
+        assert!(!yaml.contains("expires"));
+        let loaded: Suppressions = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(
+            loaded.suppressions[0].fingerprint,
+            suppressions.suppressions[0].fingerprint
+        );
+        assert_eq!(loaded.suppressions[0].file, "src/net.rs");
+
+        let missing = Path::new("/nonexistent/comgen.suppressions");
+        assert!(Suppressions::load(missing).unwrap().suppressions.is_empty());
+    }
+}
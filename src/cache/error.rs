@@ -0,0 +1,17 @@
+use thiserror::Error;
+
+/// Represents errors that can occur while using the response cache
+#[derive(Error, Debug)]
+pub enum CacheError {
+    /// Error that occurs when the cache directory cannot be resolved
+    #[error("Invalid cache path: {0}")]
+    InvalidPath(String),
+
+    /// Error that occurs when reading or writing cache entries fails
+    #[error("Cache IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Error that occurs when a cache entry cannot be (de)serialized
+    #[error("Cache entry error: {0}")]
+    Entry(#[from] serde_json::Error),
+}
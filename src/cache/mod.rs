@@ -0,0 +1,4 @@
+mod error;
+mod store;
+
+pub use store::ResponseCache;
@@ -0,0 +1,169 @@
+use super::error::CacheError;
+use crate::config::{expand_home, CacheConfig};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// A cached provider response stored as one JSON file
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    /// Creation time, in seconds since the Unix epoch
+    created_at: u64,
+    /// The response returned by the provider
+    response: String,
+}
+
+/// On-disk cache of provider responses
+///
+/// Entries are keyed by a hash of everything that influences the response
+/// (provider, model, prompt and parameters), expire after a TTL, and the
+/// oldest entries are evicted once the cache grows past its size limit.
+pub struct ResponseCache {
+    /// Directory holding the cache entries, `None` when caching is disabled
+    directory: Option<PathBuf>,
+    /// Maximum age of an entry
+    ttl: Duration,
+    /// Maximum total size of the entries, in bytes
+    max_size: u64,
+}
+
+impl ResponseCache {
+    /// Creates the response cache described by the configuration
+    ///
+    /// # Arguments
+    /// * `config` - Cache settings
+    /// * `enabled` - Whether caching is enabled for this run
+    ///
+    /// # Returns
+    /// * `Ok(ResponseCache)` - The cache, disabled if `enabled` is false
+    /// * `Err(CacheError)` - If the cache directory cannot be created
+    pub fn new(config: &CacheConfig, enabled: bool) -> Result<Self, CacheError> {
+        let directory = if enabled {
+            let directory = expand_home(Path::new(&config.path))
+                .map_err(|e| CacheError::InvalidPath(e.to_string()))?;
+            std::fs::create_dir_all(&directory)?;
+            Some(directory)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            directory,
+            ttl: Duration::from_secs(config.ttl_secs),
+            max_size: config.max_size_mb.saturating_mul(1024 * 1024),
+        })
+    }
+
+    /// Computes the cache key of a request
+    ///
+    /// # Arguments
+    /// * `parts` - Everything that influences the response, such as provider, model and prompt
+    ///
+    /// # Returns
+    /// The hex-encoded SHA-256 hash of the parts
+    pub fn key(parts: &[&str]) -> String {
+        let mut hasher = Sha256::new();
+        for part in parts {
+            // Prefix each part with its length so boundaries are unambiguous
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Looks up a response, ignoring expired or unreadable entries
+    ///
+    /// # Arguments
+    /// * `key` - The key computed by `ResponseCache::key`
+    ///
+    /// # Returns
+    /// The cached response, if a fresh entry exists
+    pub fn get(&self, key: &str) -> Option<String> {
+        let path = self.entry_path(key)?;
+        let content = std::fs::read_to_string(&path).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&content).ok()?;
+
+        if now().saturating_sub(entry.created_at) > self.ttl.as_secs() {
+            let _ = std::fs::remove_file(&path);
+            return None;
+        }
+
+        info!("cache hit: {}", key);
+        Some(entry.response)
+    }
+
+    /// Stores a response, replacing any previous entry for the same key
+    ///
+    /// Failures are logged and otherwise ignored, since the cache is only an optimization.
+    ///
+    /// # Arguments
+    /// * `key` - The key computed by `ResponseCache::key`
+    /// * `response` - The response to store
+    pub fn put(&self, key: &str, response: &str) {
+        let Some(path) = self.entry_path(key) else {
+            return;
+        };
+
+        let entry = CacheEntry {
+            created_at: now(),
+            response: response.to_string(),
+        };
+        let result = serde_json::to_vec(&entry)
+            .map_err(CacheError::from)
+            .and_then(|content| std::fs::write(&path, content).map_err(CacheError::from))
+            .and_then(|_| self.evict());
+
+        if let Err(e) = result {
+            warn!("failed to write cache entry {}: {}", key, e);
+        }
+    }
+
+    /// Returns the file path of an entry, or `None` when caching is disabled
+    fn entry_path(&self, key: &str) -> Option<PathBuf> {
+        self.directory
+            .as_ref()
+            .map(|directory| directory.join(format!("{}.json", key)))
+    }
+
+    /// Removes the oldest entries until the cache fits in its size limit
+    fn evict(&self) -> Result<(), CacheError> {
+        let Some(directory) = &self.directory else {
+            return Ok(());
+        };
+
+        let mut entries = Vec::new();
+        let mut total_size = 0;
+        for item in std::fs::read_dir(directory)? {
+            let item = item?;
+            let metadata = item.metadata()?;
+            if metadata.is_file() {
+                total_size += metadata.len();
+                entries.push((metadata.modified()?, metadata.len(), item.path()));
+            }
+        }
+
+        entries.sort();
+        for (_, size, path) in entries {
+            if total_size <= self.max_size {
+                break;
+            }
+            std::fs::remove_file(path)?;
+            total_size -= size;
+        }
+        Ok(())
+    }
+}
+
+/// Current time, in seconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,229 @@
+use crate::git::LogEntry;
+
+/// Section titles of the usual commit types
+const SECTION_TITLES: &[(&str, &str)] = &[
+    ("feat", "Features"),
+    ("fix", "Bug fixes"),
+    ("perf", "Performance"),
+    ("refactor", "Refactoring"),
+    ("docs", "Documentation"),
+    ("test", "Tests"),
+    ("build", "Build"),
+    ("ci", "Continuous integration"),
+    ("style", "Style"),
+    ("chore", "Chores"),
+    ("revert", "Reverts"),
+];
+
+/// A commit subject split into its conventional parts
+pub struct ParsedSubject<'a> {
+    /// Commit type, such as `feat`
+    pub commit_type: &'a str,
+    /// Optional scope given in parentheses
+    pub scope: Option<&'a str>,
+    /// Whether the type is followed by `!`
+    pub breaking: bool,
+    /// Description following the colon
+    pub description: &'a str,
+}
+
+/// Builds a Markdown changelog from the commits of a range
+///
+/// Commits are grouped by commit type, in the order of `commit_types` then
+/// alphabetically for the other types. Breaking changes, marked with `!` or
+/// a `BREAKING CHANGE` trailer, are also listed first, and subjects that do
+/// not follow the `type(scope): description` format end up under "Other changes".
+///
+/// # Arguments
+/// * `title` - Title of the changelog, such as the range
+/// * `entries` - The commits, newest first
+/// * `commit_types` - Allowed commit types, in the order of their sections
+///
+/// # Returns
+/// The changelog, or a single line saying there are no commits
+pub fn build_changelog(title: &str, entries: &[LogEntry], commit_types: &[String]) -> String {
+    if entries.is_empty() {
+        return format!("# {}\n\nNo changes.\n", title);
+    }
+
+    let mut breaking = Vec::new();
+    let mut sections: Vec<(&str, Vec<String>)> = Vec::new();
+    let mut other = Vec::new();
+
+    for entry in entries {
+        let Some(subject) = parse_subject(&entry.subject) else {
+            other.push(format!("- {} ({})", entry.subject, entry.hash));
+            continue;
+        };
+
+        let line = match subject.scope {
+            Some(scope) => format!("- **{}:** {} ({})", scope, subject.description, entry.hash),
+            None => format!("- {} ({})", subject.description, entry.hash),
+        };
+        if subject.breaking || entry.body.contains("BREAKING CHANGE") {
+            breaking.push(line.clone());
+        }
+        match sections
+            .iter_mut()
+            .find(|(commit_type, _)| *commit_type == subject.commit_type)
+        {
+            Some((_, lines)) => lines.push(line),
+            None => sections.push((subject.commit_type, vec![line])),
+        }
+    }
+
+    // Configured types first, in their order, then the others alphabetically
+    let rank = |commit_type: &str| {
+        commit_types
+            .iter()
+            .position(|allowed| allowed == commit_type)
+            .unwrap_or(commit_types.len())
+    };
+    sections.sort_by(|(a, _), (b, _)| rank(a).cmp(&rank(b)).then(a.cmp(b)));
+
+    let mut changelog = format!("# {}\n", title);
+    if !breaking.is_empty() {
+        changelog.push_str(&format!(
+            "\n## Breaking changes\n\n{}\n",
+            breaking.join("\n")
+        ));
+    }
+    for (commit_type, lines) in &sections {
+        changelog.push_str(&format!(
+            "\n## {}\n\n{}\n",
+            section_title(commit_type),
+            lines.join("\n")
+        ));
+    }
+    if !other.is_empty() {
+        changelog.push_str(&format!("\n## Other changes\n\n{}\n", other.join("\n")));
+    }
+    changelog
+}
+
+/// Splits a `type(scope)!: description` subject into its parts
+///
+/// # Arguments
+/// * `subject` - First line of a commit message
+///
+/// # Returns
+/// The parts of the subject, or `None` if it does not follow the format
+pub fn parse_subject(subject: &str) -> Option<ParsedSubject<'_>> {
+    let (header, description) = subject.split_once(':')?;
+    let description = description.trim();
+    let (header, breaking) = match header.strip_suffix('!') {
+        Some(header) => (header, true),
+        None => (header, false),
+    };
+    let (commit_type, scope) = match header.split_once('(') {
+        Some((commit_type, scope)) => (commit_type, Some(scope.strip_suffix(')')?)),
+        None => (header, None),
+    };
+
+    let valid_type = !commit_type.is_empty()
+        && commit_type
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    (valid_type && !description.is_empty()).then_some(ParsedSubject {
+        commit_type,
+        scope,
+        breaking,
+        description,
+    })
+}
+
+/// Returns the section title of a commit type
+fn section_title(commit_type: &str) -> String {
+    SECTION_TITLES
+        .iter()
+        .find(|(known, _)| *known == commit_type)
+        .map(|(_, title)| title.to_string())
+        .unwrap_or_else(|| commit_type.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(hash: &str, subject: &str, body: &str) -> LogEntry {
+        LogEntry {
+            hash: hash.to_string(),
+            subject: subject.to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_subject_splits_conventional_subjects() {
+        let subject = parse_subject("feat(cli)!: add the init wizard ").unwrap();
+        assert_eq!(subject.commit_type, "feat");
+        assert_eq!(subject.scope, Some("cli"));
+        assert!(subject.breaking);
+        assert_eq!(subject.description, "add the init wizard");
+
+        let subject = parse_subject("fix: handle ratios: 1:2").unwrap();
+        assert_eq!(subject.commit_type, "fix");
+        assert_eq!(subject.scope, None);
+        assert!(!subject.breaking);
+        assert_eq!(subject.description, "handle ratios: 1:2");
+    }
+
+    #[test]
+    fn parse_subject_rejects_other_subjects() {
+        for subject in [
+            "Merge branch 'main'",
+            "fix:",
+            ": missing type",
+            "fix(cli: unclosed scope",
+            "fix the bug: now",
+            "Revert \"feat: x\"",
+        ] {
+            assert!(parse_subject(subject).is_none(), "{}", subject);
+        }
+    }
+
+    #[test]
+    fn changelog_groups_commits_by_type() {
+        let entries = [
+            entry("a1", "fix(git): quote paths", ""),
+            entry("b2", "feat: add changelog", "BREAKING CHANGE: drops --log"),
+            entry("c3", "Merge branch 'main'", ""),
+            entry("d4", "chore!: bump MSRV", ""),
+            entry("e5", "feat(cli): add init", ""),
+        ];
+        let types = ["feat".to_string(), "fix".to_string()];
+
+        assert_eq!(
+            build_changelog("v1.0.0..HEAD", &entries, &types),
+            "# v1.0.0..HEAD
+
+## Breaking changes
+
+- add changelog (b2)
+- bump MSRV (d4)
+
+## Features
+
+- add changelog (b2)
+- **cli:** add init (e5)
+
+## Bug fixes
+
+- **git:** quote paths (a1)
+
+## Chores
+
+- bump MSRV (d4)
+
+## Other changes
+
+- Merge branch 'main' (c3)
+"
+        );
+    }
+
+    #[test]
+    fn changelog_without_commits_says_so() {
+        assert_eq!(build_changelog("HEAD", &[], &[]), "# HEAD\n\nNo changes.\n");
+    }
+}
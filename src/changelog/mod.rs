@@ -0,0 +1,3 @@
+mod builder;
+
+pub use builder::{build_changelog, parse_subject};
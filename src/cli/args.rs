@@ -1,19 +1,102 @@
 use super::error::CliError;
-use clap::Parser;
+use crate::audit::export::ExportFormat;
+use crate::audit::model::Severity;
+use crate::config::AuditRecord;
+use crate::git::DiffTarget;
+use crate::hook::HookKind;
+use clap::{Args, Parser, Subcommand};
 use std::path::PathBuf;
 
 /// Command line arguments parser for the cllm application
+///
+/// Running without a command commits the modified files, as `comgen commit` does.
 #[derive(Parser, Debug)]
 #[command(name = "comgen")]
 pub struct Cli {
+    /// Command to run, committing the modified files by default
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Options of the default `commit` command
+    #[command(flatten)]
+    pub commit: CommitArgs,
+
     /// Path to the configuration file
-    #[arg(long, default_value = "~/.comgen/config.yaml")]
+    #[arg(long, default_value = "~/.comgen/config.yaml", global = true)]
     pub config: PathBuf,
 
     /// Optional prefix to add to commit messages
-    #[arg(short = 'p', long = "prefix", default_value = "")]
+    #[arg(short = 'p', long = "prefix", default_value = "", global = true)]
     pub prefix: String,
 
+    /// Minimum severity of the audit findings shown (LOW, MEDIUM, HIGH, CRITICAL)
+    #[arg(long = "audit-level", default_value = "MEDIUM", global = true)]
+    pub audit_level: Severity,
+
+    /// Write the audit findings of the run to this file
+    #[arg(long = "audit-output", value_name = "PATH", global = true)]
+    pub audit_output: Option<PathBuf>,
+
+    /// Format of the audit report, guessed from the extension by default
+    #[arg(
+        long = "audit-format",
+        value_enum,
+        requires = "audit_output",
+        global = true
+    )]
+    pub audit_format: Option<ExportFormat>,
+
+    /// Audit profiles to run, comma-separated (overrides config and comgen.template)
+    #[arg(
+        long = "audit-profile",
+        value_name = "NAMES",
+        value_delimiter = ',',
+        global = true
+    )]
+    pub audit_profiles: Vec<String>,
+
+    /// Record the audit of each commit as trailers or a git note (overrides config)
+    #[arg(long = "audit-record", value_enum, value_name = "WHERE", global = true)]
+    pub audit_record: Option<AuditRecord>,
+
+    /// Maximum cost of this run, generation stops once it is reached (overrides config)
+    #[arg(long = "budget", global = true)]
+    pub budget: Option<f64>,
+
+    /// Bypass the response cache for this run
+    #[arg(long = "no-cache", default_value = "false", global = true)]
+    pub no_cache: bool,
+
+    /// Run non-interactively: accept the first valid commit message, apply the
+    /// configured audit failure policy and commit despite blocking findings
+    #[arg(long = "force", default_value = "false", global = true)]
+    pub force: bool,
+}
+
+/// Commands of comgen
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Audit the modified files and commit each of them with a generated message (default)
+    Commit(CommitArgs),
+    /// Print a commit message generated for the staged changes, without committing
+    Message,
+    /// Audit the working tree, the staged changes, a commit or a range without committing
+    Audit(AuditArgs),
+    /// Integrate with `git commit` through git hooks
+    Hook(HookArgs),
+    /// Locate, show or edit the configuration file
+    Config(ConfigArgs),
+    /// Check the configuration and the repository
+    Doctor,
+    /// Print a changelog of the commits in a range, grouped by commit type
+    Changelog(ChangelogArgs),
+    /// Create the configuration file, and optionally the template of the repository
+    Init,
+}
+
+/// Arguments of the `commit` command
+#[derive(Args, Debug, Default)]
+pub struct CommitArgs {
     /// Whether to automatically push changes after commit
     #[arg(short = 'a', long = "auto-push", default_value = "false")]
     pub auto_push: bool,
@@ -22,17 +105,142 @@ pub struct Cli {
     #[arg(short = 'm', long = "multi-file", default_value = "false")]
     pub multi_file: bool,
 
-    /// Whether to perform security audit on changes
-    #[arg(long = "audit", default_value = "true")]
+    /// Perform the security audit on changes (overrides config)
+    #[arg(long = "audit", overrides_with = "no_audit")]
     pub audit: bool,
 
-    /// Minimum severity level for audit findings
-    #[arg(long = "audit-level", default_value = "MEDIUM")]
-    pub audit_level: String,
+    /// Skip the security audit on changes (overrides config)
+    #[arg(long = "no-audit", overrides_with = "audit")]
+    pub no_audit: bool,
 
-    /// Whether to force operations without confirmation
-    #[arg(long = "force", default_value = "false")]
-    pub force: bool,
+    /// Maximum number of files processed concurrently (overrides config)
+    #[arg(short = 'j', long = "jobs")]
+    pub jobs: Option<usize>,
+}
+
+impl CommitArgs {
+    /// Returns whether auditing was enabled or disabled on the command line
+    ///
+    /// # Returns
+    /// - Some(bool) if `--audit` or `--no-audit` was given, the last one winning
+    /// - None to keep the configured setting
+    pub fn audit_override(&self) -> Option<bool> {
+        match (self.audit, self.no_audit) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// Arguments of the `config` command
+#[derive(Args, Debug)]
+pub struct ConfigArgs {
+    /// Action on the configuration file
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+/// Actions of the `config` command
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print the path of the configuration file
+    Path,
+    /// Print the configuration file, API keys masked
+    Show,
+    /// Open the configuration file in `$VISUAL` or `$EDITOR`
+    Edit,
+}
+
+/// Arguments of the `changelog` command
+#[derive(Args, Debug)]
+pub struct ChangelogArgs {
+    /// Revision range to list, such as `v1.2.0..HEAD`, from the latest tag by default
+    #[arg(value_name = "RANGE")]
+    pub range: Option<String>,
+}
+
+/// Arguments of the `hook` command
+#[derive(Args, Debug)]
+pub struct HookArgs {
+    /// Action on the hooks
+    #[command(subcommand)]
+    pub command: HookCommand,
+}
+
+/// Actions of the `hook` command
+#[derive(Subcommand, Debug)]
+pub enum HookCommand {
+    /// Install the prepare-commit-msg hook, filling commit messages from the staged diff
+    Install(HookInstallArgs),
+    /// Remove the hooks installed by comgen, restoring the hooks they chained
+    Uninstall,
+    /// Run a hook, as invoked by git
+    #[command(hide = true)]
+    Run(HookRunArgs),
+}
+
+/// Arguments of the `hook install` command
+#[derive(Args, Debug)]
+pub struct HookInstallArgs {
+    /// Also install the commit-msg hook, rejecting messages that do not follow the template
+    #[arg(long = "commit-msg", default_value = "false")]
+    pub commit_msg: bool,
+}
+
+/// Arguments of the `hook run` command
+#[derive(Args, Debug)]
+pub struct HookRunArgs {
+    /// The hook invoked by git
+    #[arg(value_enum)]
+    pub hook: HookKind,
+
+    /// Arguments git passed to the hook
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    pub args: Vec<String>,
+}
+
+/// Arguments of the `audit` command
+#[derive(Args, Debug)]
+pub struct AuditArgs {
+    /// Revision range to audit, such as `main..HEAD`
+    #[arg(value_name = "RANGE", conflicts_with_all = ["staged", "commit"])]
+    pub range: Option<String>,
+
+    /// Audit the changes staged for commit instead of the working tree
+    #[arg(long = "staged", default_value = "false", conflicts_with = "commit")]
+    pub staged: bool,
+
+    /// Audit the changes introduced by a commit
+    #[arg(long = "commit", value_name = "REV")]
+    pub commit: Option<String>,
+
+    /// Exit with an error when findings at or above this severity remain
+    /// (the gate of each profile by default when `audit.block_on_critical` is set)
+    #[arg(long = "fail-on", value_name = "SEVERITY")]
+    pub fail_on: Option<Severity>,
+
+    /// Record every current finding in the suppression file instead of showing them
+    #[arg(long = "baseline", default_value = "false")]
+    pub baseline: bool,
+}
+
+impl AuditArgs {
+    /// Returns the changes selected on the command line
+    ///
+    /// # Returns
+    /// The range, commit or staged changes if given, the working tree otherwise
+    pub fn target(&self) -> DiffTarget {
+        if let Some(range) = &self.range {
+            DiffTarget::Range(range.clone())
+        } else if let Some(commit) = &self.commit {
+            DiffTarget::Commit(commit.clone())
+        } else if self.staged {
+            DiffTarget::Staged
+        } else {
+            DiffTarget::WorkingTree
+        }
+    }
 }
 
 impl Cli {
@@ -41,6 +249,17 @@ impl Cli {
         Self::parse()
     }
 
+    /// Returns the options of the commit flow
+    ///
+    /// # Returns
+    /// The options given to `comgen commit`, or to `comgen` without a command
+    pub fn commit_args(&self) -> &CommitArgs {
+        match &self.command {
+            Some(Command::Commit(args)) => args,
+            _ => &self.commit,
+        }
+    }
+
     /// Get the configuration file path as a String
     ///
     /// # Returns
@@ -1,51 +1,19 @@
 use super::error::CliError;
-use crate::audit::model::AuditResult;
-use indicatif::{ProgressBar, ProgressStyle};
+use crate::audit::model::{AuditIssueJson, AuditResult, FindingSource, Severity};
+use crate::audit::suppression::SUPPRESSIONS_FILE;
+use crate::doctor::{CheckStatus, DoctorReport};
+use crate::usage::ModelUsage;
+use crate::utils::StringUtils;
+use chrono::NaiveDate;
 use std::io::{self, Write};
 
 /// Display handler for CLI output and user interaction
-pub struct Display {
-    /// Optional progress spinner for long-running operations
-    spinner: Option<ProgressBar>,
-}
+pub struct Display;
 
 impl Display {
     /// Creates a new Display instance
     pub fn new() -> Self {
-        Self { spinner: None }
-    }
-
-    /// Creates and displays a spinner with the given message
-    ///
-    /// # Arguments
-    /// * `message` - The message to display alongside the spinner
-    pub fn create_spinner(&mut self, message: &str) {
-        let spinner = ProgressBar::new_spinner();
-        spinner.set_style(
-            ProgressStyle::default_spinner()
-                .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏")
-                .template("{spinner} {msg}")
-                .unwrap(),
-        );
-        spinner.set_message(message.to_string());
-        self.spinner = Some(spinner);
-    }
-
-    /// Finishes and clears the current spinner if one exists
-    pub fn finish_spinner(&mut self) {
-        if let Some(spinner) = self.spinner.take() {
-            spinner.finish_and_clear();
-        }
-    }
-
-    /// Finishes the current spinner with a final message if one exists
-    ///
-    /// # Arguments
-    /// * `message` - The final message to display
-    pub fn finish_spinner_with_message(&mut self, message: &str) {
-        if let Some(spinner) = self.spinner.take() {
-            spinner.finish_with_message(message.to_string());
-        }
+        Self
     }
 
     /// Displays a list of modified files with their status
@@ -77,20 +45,43 @@ impl Display {
         println!("╰{}╯", "─".repeat(max_width));
     }
 
-    /// Prompts the user to accept or reject a commit message
+    /// Opens the commit message preview box, ready to receive streamed text
     ///
     /// # Arguments
     /// * `file` - The file being committed
-    /// * `message` - The proposed commit message
+    /// * `prefix` - Prefix prepended to the generated message, if any
+    pub fn begin_commit_preview(&self, file: &str, prefix: &str) {
+        println!("\n╭─ Commit Message Preview {}╮", "─".repeat(50 - 23));
+        println!("│ File: {}", file);
+        print!("│ Message: ");
+        if !prefix.is_empty() {
+            print!("[{}] ", prefix);
+        }
+        let _ = io::stdout().flush();
+    }
+
+    /// Writes a piece of generated text inside the open preview box
+    ///
+    /// # Arguments
+    /// * `token` - The text received from the provider
+    pub fn write_preview_token(&self, token: &str) {
+        print!("{}", token.replace('\n', "\n│ "));
+        let _ = io::stdout().flush();
+    }
+
+    /// Closes the preview box opened by `begin_commit_preview`
+    pub fn end_commit_preview(&self) {
+        println!();
+        println!("╰{}╯", "─".repeat(50));
+    }
+
+    /// Closes the preview box and prompts the user to accept or reject the message
     ///
     /// # Returns
     /// * `Ok(bool)` - True if accepted, false if rejected
     /// * `Err(CliError)` - If there's an IO error
-    pub fn prompt_commit_message(&self, file: &str, message: &String) -> Result<bool, CliError> {
-        println!("\n╭─ Commit Message Preview {}╮", "─".repeat(50 - 23));
-        println!("│ File: {}", file);
-        println!("│ Message: {}", message);
-        println!("╰{}╯", "─".repeat(50));
+    pub fn confirm_commit_message(&self) -> Result<bool, CliError> {
+        self.end_commit_preview();
 
         print!("Accept this commit message? [Y/n]: ");
         io::stdout().flush().map_err(CliError::IoError)?;
@@ -112,42 +103,364 @@ impl Display {
     /// # Returns
     /// The truncated string with "..." appended if necessary
     fn truncate_string(&self, s: &str, max_width: usize) -> String {
-        if s.len() <= max_width {
-            return s.to_string();
-        }
-        format!("{}...", &s[..max_width - 3])
+        StringUtils::truncate(s, max_width)
     }
 
-    /// Displays the results of a code audit
+    /// Displays the results of a code audit, grouped by category then by file
     ///
     /// # Arguments
     /// * `results` - The audit results to display
-    pub fn show_audit_results(&self, file: &str, results: &AuditResult) {
+    /// * `min_severity` - Findings below this severity are only counted
+    pub fn show_audit_results(&self, results: &AuditResult, min_severity: Severity) {
         println!("\n╭─ Code Audit Results {}╮", "─".repeat(50 - 20));
 
-        for issue in &results.issues {
-            let severity_color = match issue.severity.as_str() {
-                "CRITICAL" => "\x1b[31m", // Red
-                "HIGH" => "\x1b[33m",     // Yellow
-                "MEDIUM" => "\x1b[32m",   // Green
-                "LOW" => "\x1b[36m",      // Cyan
-                _ => "",
-            };
+        let mut number = 0;
+        let mut current_category = None;
+        for (category, file, indices) in self.shown_groups(results, min_severity) {
+            if current_category != Some(category) {
+                current_category = Some(category);
+                let category = category.unwrap_or("uncategorized").replace('_', " ");
+                println!("│ \x1b[1;4m{}\x1b[0m", category.to_uppercase());
+            }
+            match file {
+                Some(file) => println!("│ ▸ \x1b[1m{}\x1b[0m", file),
+                None => println!("│ ▸ \x1b[1mSeveral files\x1b[0m"),
+            }
+
+            for index in indices {
+                number += 1;
+                let issue = &results.issues[index];
+                let severity_color = match issue.severity {
+                    Severity::Critical => "\x1b[31m", // Red
+                    Severity::High => "\x1b[33m",     // Yellow
+                    Severity::Medium => "\x1b[32m",   // Green
+                    Severity::Low => "\x1b[36m",      // Cyan
+                };
+
+                let source = match issue.source {
+                    FindingSource::Llm => String::new(),
+                    source => format!(" \x1b[2m({})\x1b[0m", source),
+                };
+                println!(
+                    "│ #{} {}[{}]\x1b[0m {}{}",
+                    number, severity_color, issue.severity, issue.title, source
+                );
+                println!("│ Impact: {}", issue.impact);
+                println!("│ Suggestion: {}", issue.suggestion);
+                println!("│ Context: {}", issue.context);
+                // file:line is understood by terminals and editors as a link
+                if let (Some(file), Some(line)) = (&issue.file, issue.line_start) {
+                    println!("│ File: {}:{}", file, line);
+                }
+                self.show_excerpt(issue);
+                println!("├{}┤", "─".repeat(48));
+            }
+        }
 
+        let hidden = results
+            .issues
+            .iter()
+            .filter(|issue| issue.severity < min_severity)
+            .count();
+        if hidden > 0 {
+            println!("│ {} issues below {} hidden", hidden, min_severity);
+        }
+        if results.suppressed > 0 {
             println!(
-                "│ {}[{}]\x1b[0m {}",
-                severity_color,
-                format!("{:?}", issue.severity),
-                issue.title
+                "│ {} suppressed issues hidden ({})",
+                results.suppressed, SUPPRESSIONS_FILE
             );
-            println!("│ Impact: {}", issue.impact);
-            println!("│ Suggestion: {}", issue.suggestion);
-            println!("│ Context: {}", issue.context);
-            println!("│ File: {}", file);
-            println!("├{}┤", "─".repeat(48));
+        }
+
+        for error in &results.errors {
+            println!("│ \x1b[33m⚠ {}\x1b[0m", error);
         }
 
         println!("│ Summary: {}", results.summary);
         println!("╰{}╯", "─".repeat(50));
     }
+
+    /// Asks which of the displayed findings should be suppressed
+    ///
+    /// Findings are referred to by the number shown in the audit results.
+    ///
+    /// # Arguments
+    /// * `results` - The displayed audit results
+    /// * `min_severity` - Minimum severity of the displayed findings
+    ///
+    /// # Returns
+    /// * `Ok(Vec<usize>)` - Indices of the selected issues, empty to suppress none
+    /// * `Err(CliError)` - If there's an IO error
+    pub fn select_suppressions(
+        &self,
+        results: &AuditResult,
+        min_severity: Severity,
+    ) -> Result<Vec<usize>, CliError> {
+        let shown = self.shown_issues(results, min_severity);
+        if shown.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        loop {
+            let input = self.prompt(
+                "Suppress findings? Enter their numbers (e.g. 1,3) or press Enter to continue: ",
+            )?;
+            let selected: Result<Vec<usize>, _> = input
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|number| !number.is_empty())
+                .map(
+                    |number| match number.trim_start_matches('#').parse::<usize>() {
+                        Ok(n) if n >= 1 && n <= shown.len() => Ok(shown[n - 1]),
+                        _ => Err(number),
+                    },
+                )
+                .collect();
+            match selected {
+                Ok(selected) => return Ok(selected),
+                Err(number) => println!("No finding #{}, expected 1 to {}", number, shown.len()),
+            }
+        }
+    }
+
+    /// Asks why the selected findings are accepted and until when
+    ///
+    /// # Returns
+    /// * `Ok(Some((reason, expires)))` - The reason and the optional expiry date
+    /// * `Ok(None)` - If no reason was given, cancelling the suppression
+    /// * `Err(CliError)` - If there's an IO error
+    pub fn ask_suppression_details(&self) -> Result<Option<(String, Option<NaiveDate>)>, CliError> {
+        let reason = self.prompt("Reason (empty to cancel): ")?;
+        if reason.is_empty() {
+            return Ok(None);
+        }
+
+        loop {
+            let expires = self.prompt("Expires on (YYYY-MM-DD, empty for never): ")?;
+            if expires.is_empty() {
+                return Ok(Some((reason, None)));
+            }
+            match NaiveDate::parse_from_str(&expires, "%Y-%m-%d") {
+                Ok(date) => return Ok(Some((reason, Some(date)))),
+                Err(_) => println!("Invalid date '{}'", expires),
+            }
+        }
+    }
+
+    /// Returns the indices of the issues at or above a severity, in display order
+    fn shown_issues(&self, results: &AuditResult, min_severity: Severity) -> Vec<usize> {
+        self.shown_groups(results, min_severity)
+            .into_iter()
+            .flat_map(|(_, _, indices)| indices)
+            .collect()
+    }
+
+    /// Returns the issues at or above a severity grouped by category then by
+    /// file, in display order
+    fn shown_groups<'a>(
+        &self,
+        results: &'a AuditResult,
+        min_severity: Severity,
+    ) -> Vec<(Option<&'a str>, Option<&'a str>, Vec<usize>)> {
+        let mut groups: Vec<(Option<&str>, Option<&str>, Vec<usize>)> = Vec::new();
+        for (category, indices) in results.issues_by_category() {
+            let first_group = groups.len();
+            for index in indices {
+                let issue = &results.issues[index];
+                if issue.severity < min_severity {
+                    continue;
+                }
+                let file = issue.file.as_deref();
+                match groups[first_group..]
+                    .iter_mut()
+                    .find(|(_, group, _)| *group == file)
+                {
+                    Some((_, _, indices)) => indices.push(index),
+                    None => groups.push((category, file, vec![index])),
+                }
+            }
+        }
+        groups
+    }
+
+    /// Asks a question, the default answer being used for an empty answer
+    ///
+    /// # Arguments
+    /// * `question` - The question, without punctuation
+    /// * `default` - Answer used when none is given, empty for none
+    ///
+    /// # Returns
+    /// * `Ok(String)` - The answer or the default
+    /// * `Err(CliError)` - If there's an IO error
+    pub fn ask(&self, question: &str, default: &str) -> Result<String, CliError> {
+        let answer = if default.is_empty() {
+            self.prompt(&format!("{}: ", question))?
+        } else {
+            self.prompt(&format!("{} [{}]: ", question, default))?
+        };
+        Ok(if answer.is_empty() {
+            default.to_string()
+        } else {
+            answer
+        })
+    }
+
+    /// Asks a yes or no question
+    ///
+    /// # Arguments
+    /// * `question` - The question, without punctuation
+    /// * `default` - Answer used when none is given
+    ///
+    /// # Returns
+    /// * `Ok(bool)` - True for yes
+    /// * `Err(CliError)` - If there's an IO error
+    pub fn confirm(&self, question: &str, default: bool) -> Result<bool, CliError> {
+        let choices = if default { "Y/n" } else { "y/N" };
+        let answer = self.prompt(&format!("{}? [{}]: ", question, choices))?;
+        Ok(match answer.to_lowercase().as_str() {
+            "y" | "yes" => true,
+            "n" | "no" => false,
+            _ => default,
+        })
+    }
+
+    /// Asks to pick one of several options by number
+    ///
+    /// # Arguments
+    /// * `question` - The question, without punctuation
+    /// * `options` - The options, listed in order
+    /// * `default` - Index of the option used when none is picked
+    ///
+    /// # Returns
+    /// * `Ok(usize)` - Index of the picked option
+    /// * `Err(CliError)` - If there's an IO error
+    pub fn choose(
+        &self,
+        question: &str,
+        options: &[&str],
+        default: usize,
+    ) -> Result<usize, CliError> {
+        println!("{}:", question);
+        for (index, option) in options.iter().enumerate() {
+            println!("  {}) {}", index + 1, option);
+        }
+
+        loop {
+            let answer = self.ask("Choice", &(default + 1).to_string())?;
+            match answer.parse::<usize>() {
+                Ok(n) if n >= 1 && n <= options.len() => return Ok(n - 1),
+                _ => println!("Expected a number from 1 to {}", options.len()),
+            }
+        }
+    }
+
+    /// Asks to pick a model from a list, or to enter any other name
+    ///
+    /// # Arguments
+    /// * `models` - Models offered by the provider
+    /// * `default` - Model used when none is picked
+    ///
+    /// # Returns
+    /// * `Ok(String)` - The picked or entered model
+    /// * `Err(CliError)` - If there's an IO error
+    pub fn select_model(&self, models: &[String], default: &str) -> Result<String, CliError> {
+        println!("Available models:");
+        for (index, model) in models.iter().enumerate() {
+            println!("  {:>3}) {}", index + 1, model);
+        }
+
+        let answer = self.ask("Model (number or name)", default)?;
+        Ok(match answer.parse::<usize>() {
+            Ok(n) if n >= 1 && n <= models.len() => models[n - 1].clone(),
+            _ => answer,
+        })
+    }
+
+    /// Prints a question and reads the trimmed answer
+    fn prompt(&self, question: &str) -> Result<String, CliError> {
+        print!("{}", question);
+        io::stdout().flush().map_err(CliError::IoError)?;
+
+        let mut input = String::new();
+        io::stdin()
+            .read_line(&mut input)
+            .map_err(CliError::IoError)?;
+        Ok(input.trim().to_string())
+    }
+
+    /// Displays the code around an issue, highlighting the lines it concerns
+    ///
+    /// # Arguments
+    /// * `issue` - The issue, with its line range and excerpt
+    fn show_excerpt(&self, issue: &AuditIssueJson) {
+        let (Some(start), Some(end)) = (issue.line_start, issue.line_end) else {
+            return;
+        };
+
+        for line in &issue.excerpt {
+            let Some(number) = line.new_line else {
+                continue;
+            };
+            let content = self.truncate_string(&line.content, 40);
+            if number >= start && number <= end {
+                println!("│ \x1b[1;31m> {:>5} │ {}\x1b[0m", number, content);
+            } else {
+                println!("│ \x1b[2m  {:>5} │ {}\x1b[0m", number, content);
+            }
+        }
+    }
+
+    /// Displays the tokens and cost consumed during the run
+    ///
+    /// # Arguments
+    /// * `summary` - Usage of every model used during the run
+    /// * `total_cost` - Total cost of the priced models
+    /// * `budget` - Budget of the run, if any
+    pub fn show_usage_summary(&self, summary: &[ModelUsage], total_cost: f64, budget: Option<f64>) {
+        if summary.is_empty() {
+            return;
+        }
+
+        println!("\n╭─ Token Usage {}╮", "─".repeat(50 - 13));
+        for model_usage in summary {
+            let cost = match model_usage.cost {
+                Some(cost) => format!("${:.4}", cost),
+                None => "no price".to_string(),
+            };
+            println!(
+                "│ {}: {} prompt + {} completion tokens ({})",
+                model_usage.model,
+                model_usage.usage.prompt_tokens,
+                model_usage.usage.completion_tokens,
+                cost
+            );
+        }
+        match budget {
+            Some(budget) => println!("│ Total: ${:.4} of ${:.4} budget", total_cost, budget),
+            None => println!("│ Total: ${:.4}", total_cost),
+        }
+        println!("╰{}╯", "─".repeat(50));
+    }
+
+    /// Displays the result of the diagnostic checks, with fixes for the failed ones
+    ///
+    /// # Arguments
+    /// * `report` - The checks run by `comgen doctor`
+    pub fn show_doctor_report(&self, report: &DoctorReport) {
+        println!("\n╭─ comgen doctor {}╮", "─".repeat(50 - 15));
+        for check in &report.checks {
+            let (color, symbol) = match check.status {
+                CheckStatus::Pass => ("\x1b[32m", "✓"),
+                CheckStatus::Warn => ("\x1b[33m", "!"),
+                CheckStatus::Fail => ("\x1b[31m", "✗"),
+            };
+            println!(
+                "│ {}{}\x1b[0m {}: {}",
+                color, symbol, check.name, check.detail
+            );
+            if let Some(fix) = &check.fix {
+                println!("│   \x1b[2m→ {}\x1b[0m", fix);
+            }
+        }
+        println!("╰{}╯", "─".repeat(50));
+    }
 }
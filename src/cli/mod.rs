@@ -2,5 +2,5 @@ mod args;
 mod display;
 mod error;
 
-pub use args::Cli;
+pub use args::{AuditArgs, ChangelogArgs, Cli, Command, ConfigCommand, HookCommand, HookRunArgs};
 pub use display::Display;
@@ -11,6 +11,10 @@ pub enum ConfigError {
     #[error("Failed to parse config file: {0}")]
     ParseError(#[from] serde_yaml::Error),
 
+    /// Error that occurs when the configuration file does not exist
+    #[error("Config file {0} not found, run `comgen init` to create it")]
+    NotFound(String),
+
     /// Error that occurs when a required environment variable is not set
     #[error("Environment variable not set: {0}")]
     EnvVarError(String),
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tracing::info;
 
 use super::error::ConfigError;
@@ -25,33 +25,39 @@ impl ConfigLoader {
     /// * `Ok(PathBuf)` - The expanded absolute path
     /// * `Err(ConfigError)` - If environment variables are missing
     pub fn expand_path(&self) -> Result<PathBuf, ConfigError> {
-        if self.config_path.starts_with("~/") || self.config_path.starts_with("~\\") {
-            let home = if cfg!(windows) {
-                std::env::var("USERPROFILE")
-                    .map_err(|_| ConfigError::EnvVarError("USERPROFILE".to_string()))?
-            } else {
-                std::env::var("HOME").map_err(|_| ConfigError::EnvVarError("HOME".to_string()))?
-            };
-
-            Ok(PathBuf::from(home).join(&self.config_path.to_str().unwrap()[2..]))
-        } else {
-            Ok(self.config_path.clone())
-        }
+        expand_home(&self.config_path)
     }
 
-    /// Loads and parses the configuration file
+    /// Reads and parses the configuration file, without validating it
+    ///
+    /// API keys left empty are read from the environment.
     ///
     /// # Returns
     /// * `Ok(Config)` - The parsed configuration
-    /// * `Err(ConfigError)` - If reading or parsing fails
-    pub fn load(&self) -> Result<Config, ConfigError> {
+    /// * `Err(ConfigError)` - If the file is missing, or reading or parsing fails
+    pub fn read(&self) -> Result<Config, ConfigError> {
         let expanded_path = self.expand_path()?;
         info!("Loading config from: {:?}", expanded_path);
 
+        if !expanded_path.exists() {
+            return Err(ConfigError::NotFound(expanded_path.display().to_string()));
+        }
         let config_str =
             std::fs::read_to_string(&expanded_path).map_err(ConfigError::FileReadError)?;
 
-        let config: Config = serde_yaml::from_str(&config_str).map_err(ConfigError::ParseError)?;
+        let mut config: Config =
+            serde_yaml::from_str(&config_str).map_err(ConfigError::ParseError)?;
+        config.apply_environment();
+        Ok(config)
+    }
+
+    /// Loads and parses the configuration file
+    ///
+    /// # Returns
+    /// * `Ok(Config)` - The parsed configuration
+    /// * `Err(ConfigError)` - If reading, parsing or validation fails
+    pub fn load(&self) -> Result<Config, ConfigError> {
+        let config = self.read()?;
 
         if let Err(msg) = config.validate() {
             return Err(ConfigError::InvalidPath(msg));
@@ -61,6 +67,32 @@ impl ConfigLoader {
     }
 }
 
+/// Expands a path starting with `~`, resolving the home directory
+///
+/// # Arguments
+/// * `path` - The path to expand
+///
+/// # Returns
+/// * `Ok(PathBuf)` - The expanded absolute path, or the path unchanged
+/// * `Err(ConfigError)` - If environment variables are missing
+pub fn expand_home(path: &Path) -> Result<PathBuf, ConfigError> {
+    if path.starts_with("~/") || path.starts_with("~\\") {
+        let home = if cfg!(windows) {
+            std::env::var("USERPROFILE")
+                .map_err(|_| ConfigError::EnvVarError("USERPROFILE".to_string()))?
+        } else {
+            std::env::var("HOME").map_err(|_| ConfigError::EnvVarError("HOME".to_string()))?
+        };
+
+        let relative = path
+            .to_str()
+            .ok_or_else(|| ConfigError::InvalidPath(path.display().to_string()))?;
+        Ok(PathBuf::from(home).join(&relative[2..]))
+    } else {
+        Ok(path.to_path_buf())
+    }
+}
+
 /// Helper function to load configuration from a path
 ///
 /// # Arguments
@@ -73,3 +105,71 @@ pub fn load_config(config_path: &str) -> Result<Config, ConfigError> {
     let loader = ConfigLoader::new(PathBuf::from(config_path));
     loader.load()
 }
+
+/// Helper function to read configuration from a path, without validating it
+///
+/// # Arguments
+/// * `config_path` - String path to the configuration file
+///
+/// # Returns
+/// * `Ok(Config)` - The parsed configuration
+/// * `Err(ConfigError)` - If reading or parsing fails
+pub fn read_config(config_path: &str) -> Result<Config, ConfigError> {
+    ConfigLoader::new(PathBuf::from(config_path)).read()
+}
+
+/// Masks the values of secret keys in a configuration file
+///
+/// Keys ending with `api_key`, `token` or `secret` have their value replaced,
+/// so the file can be shown or shared without leaking credentials.
+///
+/// # Arguments
+/// * `content` - Content of the configuration file
+///
+/// # Returns
+/// The content with secret values replaced by `********`
+pub fn mask_secrets(content: &str) -> String {
+    content
+        .lines()
+        .map(|line| match line.split_once(':') {
+            Some((key, value))
+                if !value.trim().is_empty()
+                    && ["api_key", "token", "secret"]
+                        .iter()
+                        .any(|secret| key.trim().to_lowercase().ends_with(secret)) =>
+            {
+                format!("{}: ********", key)
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mask_secrets_hides_credentials() {
+        let content = "openai_api_key: \"sk-123\"\n\
+                       fallback:\n  - provider: openai\n    api_key: sk-456\n\
+                       github_token: ghp_789\nclient_secret: 'abc'\n";
+        let masked = mask_secrets(content);
+        assert_eq!(
+            masked,
+            "openai_api_key: ********\n\
+             fallback:\n  - provider: openai\n    api_key: ********\n\
+             github_token: ********\nclient_secret: ********\n"
+        );
+    }
+
+    #[test]
+    fn mask_secrets_keeps_other_settings() {
+        let content = "ollama_url: \"http://localhost:11434\"\n\
+                       generation:\n  max_tokens: 200\n\
+                       anthropic_api_key:\n";
+        assert_eq!(mask_secrets(content), content);
+    }
+}
@@ -1,6 +1,12 @@
 mod error;
 mod loader;
 mod model;
+mod profiles;
 
-pub use loader::load_config;
-pub use model::Config;
+pub use loader::{expand_home, load_config, mask_secrets, read_config};
+pub use model::{
+    local_template_path, AuditConfig, AuditFailurePolicy, AuditMode, AuditRecord, CacheConfig,
+    CommitTemplate, Config, GenerationConfig, GenerationParams, LocalTemplate, ModelPrice,
+    ProviderEntry, RequestConfig, RulesConfig, ANTHROPIC_API_KEY_VAR, LOCAL_TEMPLATE_FILE,
+    OPENAI_API_KEY_VAR,
+};
@@ -1,8 +1,14 @@
+use crate::audit::model::Severity;
 use crate::config::error::ConfigError;
-use serde::Deserialize;
+use crate::config::profiles::{builtin_profile, BUILTIN_PROFILES};
+use crate::git::GitCommands;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
 
 /// Represents a commit message template with formatting rules and examples
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct CommitTemplate {
     /// The actual template string
     pub template: String,
@@ -13,12 +19,71 @@ pub struct CommitTemplate {
 }
 
 /// Local template configuration containing commit types and output format
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct LocalTemplate {
     /// List of valid commit types (e.g. "feat", "fix", etc)
     pub commit_types: Vec<String>,
     /// Template for formatting commit messages
     pub output_format: CommitTemplate,
+    /// Audit profiles run for this repository, replacing `audit.active_profiles`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub audit_profiles: Vec<String>,
+}
+
+impl LocalTemplate {
+    /// Checks that a commit message follows the template
+    ///
+    /// The first line must start with one of the commit types, optionally
+    /// followed by a scope and `!`, then `:`, and fit in the maximum length.
+    /// An empty list of types or a zero maximum length disables that check.
+    /// A `[prefix] ` added by `--prefix` is ignored.
+    ///
+    /// # Arguments
+    /// * `message` - The commit message, with or without prefix
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the message is valid
+    /// * `Err(String)` - The reason the message is invalid
+    pub fn validate_message(&self, message: &str) -> Result<(), String> {
+        let subject = strip_prefix(message.lines().next().unwrap_or_default().trim());
+        if subject.is_empty() {
+            return Err("commit message is empty".to_string());
+        }
+
+        let max_length = self.output_format.max_length;
+        if max_length > 0 && subject.chars().count() > max_length {
+            return Err(format!("subject is longer than {} characters", max_length));
+        }
+
+        if self.commit_types.is_empty() {
+            return Ok(());
+        }
+        let Some((header, _)) = subject.split_once(':') else {
+            return Err("subject has no commit type".to_string());
+        };
+        let header = header.trim_end_matches('!');
+        let commit_type = match header.split_once('(') {
+            Some((commit_type, scope)) if scope.ends_with(')') => commit_type,
+            Some(_) => return Err(format!("malformed scope in '{}'", header)),
+            None => header,
+        };
+        if !self
+            .commit_types
+            .iter()
+            .any(|allowed| allowed == commit_type)
+        {
+            return Err(format!("unknown commit type '{}'", commit_type));
+        }
+        Ok(())
+    }
+}
+
+/// Removes the `[prefix] ` added by `--prefix` before a subject
+fn strip_prefix(subject: &str) -> &str {
+    subject
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .map_or(subject, |(_, subject)| subject.trim_start())
 }
 
 /// Configuration for security audit functionality
@@ -26,14 +91,490 @@ pub struct LocalTemplate {
 pub struct AuditConfig {
     /// Whether security auditing is enabled
     pub enabled: bool,
-    /// Prompt template for security auditing
+    /// Prompt template of the `general` audit profile
+    #[serde(default)]
+    pub prompt: String,
+    /// Model used for audits instead of the main model
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Whether audits request schema-constrained JSON from providers supporting it
+    #[serde(default = "default_structured")]
+    pub structured: bool,
+    /// Whether findings reaching the gate of their profile block the commit unless `--force` is given
+    #[serde(default = "default_block_on_critical")]
+    pub block_on_critical: bool,
+    /// What to do when the audit of a file fails
+    #[serde(default)]
+    pub on_failure: AuditFailurePolicy,
+    /// Whether the diff is audited by the model, disable to only apply the local rules
+    #[serde(default = "default_llm")]
+    pub llm: bool,
+    /// Local rules applied to the added lines of the diff
+    #[serde(default)]
+    pub rules: RulesConfig,
+    /// Whether files are audited one by one, together, or both
+    #[serde(default)]
+    pub mode: AuditMode,
+    /// Maximum size in characters of the diff sent in one combined audit request
+    #[serde(default = "default_max_diff_chars")]
+    pub max_diff_chars: usize,
+    /// Audit profiles defined or overridden by the user, by name
+    #[serde(default)]
+    pub profiles: HashMap<String, AuditProfile>,
+    /// Profiles run on each audit, one request each
+    #[serde(default = "default_active_profiles")]
+    pub active_profiles: Vec<String>,
+    /// How the audit of each commit is recorded in the repository
+    #[serde(default)]
+    pub record: AuditRecord,
+}
+
+impl AuditConfig {
+    /// Returns a profile defined in the configuration or built in
+    ///
+    /// `general` is built from `audit.prompt`.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the profile
+    ///
+    /// # Returns
+    /// The profile, or `None` if no profile has this name
+    pub fn profile(&self, name: &str) -> Option<AuditProfile> {
+        if let Some(profile) = self.profiles.get(name) {
+            return Some(profile.clone());
+        }
+        match name {
+            "general" => Some(AuditProfile::new(self.prompt.clone())),
+            name => builtin_profile(name),
+        }
+    }
+
+    /// Returns the severity from which findings of a category block the commit
+    ///
+    /// Categories without a profile, such as those of local rules, use CRITICAL.
+    ///
+    /// # Arguments
+    /// * `category` - Category of the finding
+    pub fn fail_on(&self, category: Option<&str>) -> Severity {
+        category
+            .and_then(|category| self.profile(category))
+            .map(|profile| profile.fail_on)
+            .unwrap_or(Severity::Critical)
+    }
+
+    /// Checks that every active profile exists and has a prompt
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the profiles can be run
+    /// * `Err(String)` - Describing the first invalid profile
+    pub fn validate_profiles(&self) -> Result<(), String> {
+        if self.active_profiles.is_empty() {
+            return Err("At least one audit profile must be active".to_string());
+        }
+        for name in &self.active_profiles {
+            let Some(profile) = self.profile(name) else {
+                return Err(format!(
+                    "Unknown audit profile '{}', expected general, {} or a profile of audit.profiles",
+                    name,
+                    BUILTIN_PROFILES.join(", ")
+                ));
+            };
+            if profile.prompt.trim().is_empty() {
+                return Err(format!("Audit profile '{}' has an empty prompt", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A focused audit, run as its own request
+#[derive(Deserialize, Clone, Debug)]
+pub struct AuditProfile {
+    /// Prompt template, `{{git_diff}}` being replaced by the diff
     pub prompt: String,
+    /// Severities reported by the model replaced by others, e.g. `CRITICAL: HIGH`
+    #[serde(default)]
+    pub severities: HashMap<Severity, Severity>,
+    /// Findings at or above this severity block the commit when `block_on_critical` is set
+    #[serde(default = "default_fail_on")]
+    pub fail_on: Severity,
+}
+
+impl AuditProfile {
+    /// Creates a profile keeping the reported severities and gating on CRITICAL
+    ///
+    /// # Arguments
+    /// * `prompt` - Prompt template of the profile
+    pub fn new(prompt: String) -> Self {
+        Self {
+            prompt,
+            severities: HashMap::new(),
+            fail_on: default_fail_on(),
+        }
+    }
+
+    /// Returns the severity of a finding after the profile's mapping
+    ///
+    /// # Arguments
+    /// * `severity` - Severity reported by the model
+    pub fn map_severity(&self, severity: Severity) -> Severity {
+        self.severities.get(&severity).copied().unwrap_or(severity)
+    }
+}
+
+/// How the changes are split into audit requests
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditMode {
+    /// One request per file
+    #[default]
+    PerFile,
+    /// One request for the whole change set, split only to fit `max_diff_chars`
+    Combined,
+    /// A combined audit followed by per-file audits
+    Both,
+}
+
+impl AuditMode {
+    /// Checks whether the whole change set is audited at once
+    pub fn combined(self) -> bool {
+        matches!(self, AuditMode::Combined | AuditMode::Both)
+    }
+
+    /// Checks whether every file is audited on its own
+    pub fn per_file(self) -> bool {
+        matches!(self, AuditMode::PerFile | AuditMode::Both)
+    }
+}
+
+/// Where the audit of a commit is recorded
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditRecord {
+    /// The audit leaves no trace in the repository
+    #[default]
+    None,
+    /// `Audited-by` and `Audit-Findings` trailers appended to the commit message
+    Trailers,
+    /// The full result as JSON in a note under `refs/notes/comgen-audit`
+    Notes,
+}
+
+/// Configuration of the local audit rules
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RulesConfig {
+    /// Whether the local rules are applied
+    pub enabled: bool,
+    /// Identifiers of the built-in rules to skip
+    pub disabled: Vec<String>,
+    /// Additional rules defined by the user
+    pub custom: Vec<CustomRule>,
+}
+
+impl Default for RulesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            disabled: Vec::new(),
+            custom: Vec::new(),
+        }
+    }
+}
+
+/// A user-defined audit rule matching added lines against a regular expression
+#[derive(Deserialize, Clone, Debug)]
+pub struct CustomRule {
+    /// Identifier of the rule
+    pub id: String,
+    /// Title of the findings
+    pub title: String,
+    /// Regular expression matched against each added line
+    pub pattern: String,
+    /// Severity of the findings
+    #[serde(default = "default_rule_severity")]
+    pub severity: Severity,
+    /// Consequences of the issue
+    #[serde(default)]
+    pub impact: String,
+    /// How to resolve the issue
+    #[serde(default)]
+    pub suggestion: String,
+    /// File extensions the rule applies to, every file if empty
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// Category the findings are grouped and gated under
+    #[serde(default = "default_rule_category")]
+    pub category: String,
+}
+
+/// Action taken when the audit of a file fails
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditFailurePolicy {
+    /// Ask the user, or abort when running with `--force`
+    #[default]
+    Ask,
+    /// Commit the file without audit
+    Continue,
+    /// Stop processing
+    Abort,
+}
+
+/// Findings reaching the gate of their profile block commits unless disabled
+fn default_block_on_critical() -> bool {
+    true
+}
+
+/// Diffs are audited by the model unless disabled
+fn default_llm() -> bool {
+    true
+}
+
+/// Default size budget of a combined audit request, roughly 12k tokens
+fn default_max_diff_chars() -> usize {
+    48_000
+}
+
+/// Custom rules report MEDIUM findings unless configured otherwise
+fn default_rule_severity() -> Severity {
+    Severity::Medium
+}
+
+/// Custom rules are grouped under their own category unless configured otherwise
+fn default_rule_category() -> String {
+    "custom".to_string()
+}
+
+/// Profiles block commits on CRITICAL findings unless configured otherwise
+fn default_fail_on() -> Severity {
+    Severity::Critical
+}
+
+/// Audits run the general profile, built from `audit.prompt`, unless configured otherwise
+fn default_active_profiles() -> Vec<String> {
+    vec!["general".to_string()]
+}
+
+/// Structured audit output is requested unless disabled
+fn default_structured() -> bool {
+    true
+}
+
+/// Sampling parameters sent with a generation request
+///
+/// Unset values are left to the provider's defaults.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct GenerationParams {
+    /// Sampling temperature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    /// Maximum number of tokens to generate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Nucleus sampling probability mass
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    /// Sequences stopping the generation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+}
+
+impl GenerationParams {
+    /// Checks that the parameters are within the ranges accepted by providers
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(temperature) = self.temperature {
+            if !(0.0..=2.0).contains(&temperature) {
+                return Err("temperature must be between 0 and 2".to_string());
+            }
+        }
+        if let Some(top_p) = self.top_p {
+            if !(0.0..=1.0).contains(&top_p) {
+                return Err("top_p must be between 0 and 1".to_string());
+            }
+        }
+        if self.max_tokens == Some(0) {
+            return Err("max_tokens must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// Returns these parameters with every value set in `overrides` replaced
+    ///
+    /// # Arguments
+    /// * `overrides` - Parameters taking precedence over these ones
+    pub fn merge(&self, overrides: &GenerationParams) -> GenerationParams {
+        GenerationParams {
+            temperature: overrides.temperature.or(self.temperature),
+            max_tokens: overrides.max_tokens.or(self.max_tokens),
+            top_p: overrides.top_p.or(self.top_p),
+            stop: if overrides.stop.is_empty() {
+                self.stop.clone()
+            } else {
+                overrides.stop.clone()
+            },
+        }
+    }
+}
+
+/// Generation parameters with overrides per provider and per task
+///
+/// Parameters are resolved from `defaults`, then the provider's entry, then
+/// the task (`commit` or `audit`), the most specific value winning.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct GenerationConfig {
+    /// Parameters applied to every request
+    pub defaults: GenerationParams,
+    /// Overrides for commit message generation
+    pub commit: GenerationParams,
+    /// Overrides for code audits
+    pub audit: GenerationParams,
+    /// Overrides keyed by provider type (e.g. "ollama")
+    pub providers: HashMap<String, GenerationParams>,
+}
+
+impl GenerationConfig {
+    /// Returns the parameters of a provider, before task overrides
+    ///
+    /// # Arguments
+    /// * `provider` - The provider type
+    pub fn for_provider(&self, provider: &str) -> GenerationParams {
+        match self.providers.get(provider) {
+            Some(overrides) => self.defaults.merge(overrides),
+            None => self.defaults.clone(),
+        }
+    }
+}
+
+/// Configuration for the external command provider
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct CommandProviderConfig {
+    /// Executable to spawn for each request
+    pub program: String,
+    /// Extra arguments passed to the executable
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// A provider and model used as part of the provider chain
+#[derive(Deserialize, Clone, Debug)]
+pub struct ProviderEntry {
+    /// AI provider to use (e.g. "openai", "anthropic", "ollama", "command")
+    pub provider: String,
+    /// Model name to use with the provider
+    pub model: String,
+    /// API key overriding the provider's key from the main configuration
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Ollama URL overriding `ollama_url` from the main configuration
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+/// Timeout and retry settings applied to provider requests
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RequestConfig {
+    /// Maximum duration of a non-streamed request, in seconds
+    pub timeout_secs: u64,
+    /// Maximum duration to establish a connection, in seconds
+    pub connect_timeout_secs: u64,
+    /// Maximum idle time between two reads of a response, in seconds
+    pub read_timeout_secs: u64,
+    /// Number of retries after a transient failure
+    pub max_retries: u32,
+    /// Delay before the first retry, in milliseconds
+    pub initial_backoff_ms: u64,
+    /// Upper bound of the retry delay, in milliseconds
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RequestConfig {
+    /// Creates request settings suitable for both remote APIs and local models
+    fn default() -> Self {
+        Self {
+            timeout_secs: 120,
+            connect_timeout_secs: 10,
+            read_timeout_secs: 60,
+            max_retries: 3,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+        }
+    }
 }
 
+/// Settings of the on-disk response cache
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Whether responses are cached
+    pub enabled: bool,
+    /// Directory holding the cache entries
+    pub path: String,
+    /// Maximum age of a cached response, in seconds
+    pub ttl_secs: u64,
+    /// Maximum total size of the cache, in megabytes
+    pub max_size_mb: u64,
+}
+
+impl Default for CacheConfig {
+    /// Creates a cache under the comgen data directory keeping responses for a week
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: "~/.comgen/cache".to_string(),
+            ttl_secs: 7 * 24 * 60 * 60,
+            max_size_mb: 50,
+        }
+    }
+}
+
+/// Price of a model, in currency units per million tokens
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct ModelPrice {
+    /// Price of one million prompt tokens
+    pub input_per_million: f64,
+    /// Price of one million completion tokens
+    pub output_per_million: f64,
+}
+
+/// Token usage accounting settings
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct UsageConfig {
+    /// File receiving one JSON line per model and run
+    pub ledger: String,
+    /// Maximum cost of a single run, generation stops once it is reached
+    pub budget: Option<f64>,
+    /// Prices of the models, keyed by model name
+    pub pricing: HashMap<String, ModelPrice>,
+}
+
+impl Default for UsageConfig {
+    /// Creates a ledger under the comgen data directory, without budget or prices
+    fn default() -> Self {
+        Self {
+            ledger: "~/.comgen/usage.jsonl".to_string(),
+            budget: None,
+            pricing: HashMap::new(),
+        }
+    }
+}
+
+/// Template file committed at the root of the repository
+pub const LOCAL_TEMPLATE_FILE: &str = "comgen.template";
+
+/// Environment variable read when `openai_api_key` is empty
+pub const OPENAI_API_KEY_VAR: &str = "OPENAI_API_KEY";
+
+/// Environment variable read when `anthropic_api_key` is empty
+pub const ANTHROPIC_API_KEY_VAR: &str = "ANTHROPIC_API_KEY";
+
 /// Main configuration struct for the application
 #[derive(Deserialize, Clone, Debug)]
 pub struct Config {
-    /// AI provider to use (e.g. "openai", "anthropic", "ollama")
+    /// AI provider to use (e.g. "openai", "anthropic", "ollama", "command")
     pub provider: String,
     /// Model name to use with the provider
     pub model: String,
@@ -47,8 +588,42 @@ pub struct Config {
     pub openai_api_key: String,
     /// URL for Ollama API endpoint
     pub ollama_url: String,
+    /// External command used by the "command" provider
+    #[serde(default)]
+    pub command: CommandProviderConfig,
     /// Security audit configuration
     pub audit: AuditConfig,
+    /// Maximum number of files processed concurrently
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// Whether to stream generated messages into the preview
+    #[serde(default = "default_stream")]
+    pub stream: bool,
+    /// Timeout and retry settings for provider requests
+    #[serde(default)]
+    pub request: RequestConfig,
+    /// Providers tried in order when the main provider fails with a retryable error
+    #[serde(default)]
+    pub fallback: Vec<ProviderEntry>,
+    /// Response cache settings
+    #[serde(default)]
+    pub cache: CacheConfig,
+    /// Token usage, pricing and budget settings
+    #[serde(default)]
+    pub usage: UsageConfig,
+    /// Generation parameters per provider and per task
+    #[serde(default)]
+    pub generation: GenerationConfig,
+}
+
+/// Default number of files processed concurrently
+fn default_concurrency() -> usize {
+    4
+}
+
+/// Streaming is enabled unless explicitly disabled
+fn default_stream() -> bool {
+    true
 }
 
 impl Default for Config {
@@ -65,53 +640,287 @@ impl Default for Config {
                     max_length: 0,
                     examples: Vec::new(),
                 },
+                audit_profiles: Vec::new(),
             },
             anthropic_api_key: String::new(),
             openai_api_key: String::new(),
             ollama_url: "http://localhost:11434".to_string(),
+            command: CommandProviderConfig::default(),
             audit: AuditConfig {
                 enabled: false,
                 prompt: String::new(),
+                model: None,
+                structured: true,
+                block_on_critical: true,
+                on_failure: AuditFailurePolicy::default(),
+                llm: true,
+                rules: RulesConfig::default(),
+                mode: AuditMode::default(),
+                max_diff_chars: default_max_diff_chars(),
+                profiles: HashMap::new(),
+                active_profiles: default_active_profiles(),
+                record: AuditRecord::default(),
             },
+            concurrency: default_concurrency(),
+            stream: default_stream(),
+            request: RequestConfig::default(),
+            fallback: Vec::new(),
+            cache: CacheConfig::default(),
+            usage: UsageConfig::default(),
+            generation: GenerationConfig::default(),
         }
     }
 }
 
 impl Config {
-    /// Validates the configuration based on the selected provider
+    /// Validates the configuration based on the selected providers
     ///
-    /// Returns an error if required credentials are missing for the selected provider
+    /// Returns an error if required credentials are missing for any provider of the chain
     pub fn validate(&self) -> Result<(), String> {
-        match self.provider.as_str() {
-            "openai" if self.openai_api_key.is_empty() => {
+        for entry in self.provider_chain() {
+            self.validate_provider(&entry)?;
+        }
+        self.validate_settings()
+    }
+
+    /// Validates the settings other than the providers' credentials
+    ///
+    /// Returns an error naming the first invalid setting
+    pub fn validate_settings(&self) -> Result<(), String> {
+        if self.concurrency == 0 {
+            return Err("Concurrency must be at least 1".to_string());
+        }
+
+        if self.audit.max_diff_chars == 0 {
+            return Err("Audit max_diff_chars must be at least 1".to_string());
+        }
+
+        let generation = &self.generation;
+        let params = [
+            ("defaults", &generation.defaults),
+            ("commit", &generation.commit),
+            ("audit", &generation.audit),
+        ]
+        .into_iter()
+        .chain(
+            generation
+                .providers
+                .iter()
+                .map(|(name, params)| (name.as_str(), params)),
+        );
+        for (name, params) in params {
+            params
+                .validate()
+                .map_err(|e| format!("Invalid generation parameters for {}: {}", name, e))?;
+        }
+
+        for rule in &self.audit.rules.custom {
+            regex::Regex::new(&rule.pattern)
+                .map_err(|e| format!("Invalid pattern for audit rule {}: {}", rule.id, e))?;
+        }
+        Ok(())
+    }
+
+    /// Reads the API keys left empty from the environment
+    ///
+    /// `OPENAI_API_KEY` and `ANTHROPIC_API_KEY` keep keys out of the configuration file.
+    pub fn apply_environment(&mut self) {
+        let keys = [
+            (&mut self.openai_api_key, OPENAI_API_KEY_VAR),
+            (&mut self.anthropic_api_key, ANTHROPIC_API_KEY_VAR),
+        ];
+        for (key, var) in keys {
+            if key.is_empty() {
+                if let Ok(value) = std::env::var(var) {
+                    *key = value;
+                }
+            }
+        }
+    }
+
+    /// Checks that a provider of the chain has the settings it requires
+    ///
+    /// # Arguments
+    /// * `entry` - A provider of the chain, as returned by `provider_chain`
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the provider is known and configured
+    /// * `Err(String)` - Naming the missing credential or setting
+    pub fn validate_provider(&self, entry: &ProviderEntry) -> Result<(), String> {
+        let api_key = entry.api_key.as_deref().unwrap_or_default();
+        let url = entry.url.as_deref().unwrap_or_default();
+
+        match entry.provider.as_str() {
+            "openai" if api_key.is_empty() => {
                 Err("OpenAI API key is required when using OpenAI provider".to_string())
             }
-            "anthropic" if self.anthropic_api_key.is_empty() => {
+            "anthropic" if api_key.is_empty() => {
                 Err("Anthropic API key is required when using Anthropic provider".to_string())
             }
-            "ollama" if self.ollama_url.is_empty() => {
+            "ollama" if url.is_empty() => {
                 Err("Ollama URL is required when using Ollama provider".to_string())
             }
-            _ => Ok(()),
+            "command" if self.command.program.is_empty() => {
+                Err("Command program is required when using command provider".to_string())
+            }
+            "openai" | "anthropic" | "ollama" | "command" => Ok(()),
+            other => Err(format!("Unknown provider type: {}", other)),
         }
     }
 
+    /// Returns the main provider followed by the fallback providers
+    ///
+    /// Each entry's API key and URL are resolved from the main configuration
+    /// when the entry does not override them.
+    pub fn provider_chain(&self) -> Vec<ProviderEntry> {
+        self.provider_chain_with_model(&self.model)
+    }
+
+    /// Returns the provider chain with another model for the main provider
+    ///
+    /// # Arguments
+    /// * `model` - Model used by the main provider, e.g. the audit model
+    pub fn provider_chain_with_model(&self, model: &str) -> Vec<ProviderEntry> {
+        let main = ProviderEntry {
+            provider: self.provider.clone(),
+            model: model.to_string(),
+            api_key: None,
+            url: None,
+        };
+
+        std::iter::once(main)
+            .chain(self.fallback.iter().cloned())
+            .map(|mut entry| {
+                let default_key = match entry.provider.as_str() {
+                    "openai" => &self.openai_api_key,
+                    "anthropic" => &self.anthropic_api_key,
+                    _ => "",
+                };
+                entry.api_key.get_or_insert_with(|| default_key.to_string());
+                entry.url.get_or_insert_with(|| self.ollama_url.clone());
+                entry
+            })
+            .collect()
+    }
+
     /// Loads template configuration from a local file if it exists
     ///
-    /// Looks for a file named "comgen.template" in the current directory and updates
-    /// the configuration with its contents if found
+    /// Looks for a file named "comgen.template" at the root of the repository and
+    /// updates the configuration with its contents if found
     pub fn load_local_template(&mut self) -> Result<(), ConfigError> {
-        let local_template_path = std::path::Path::new("comgen.template");
+        let local_template_path = local_template_path();
 
         if local_template_path.exists() {
-            let template_content = std::fs::read_to_string(local_template_path)?;
+            let template_content = std::fs::read_to_string(&local_template_path)?;
             let local_template: LocalTemplate = serde_yaml::from_str(&template_content)?;
 
             // Replace existing templates with those from local file
             self.templates.commit_types = local_template.commit_types;
             self.templates.output_format = local_template.output_format;
+            if !local_template.audit_profiles.is_empty() {
+                self.audit.active_profiles = local_template.audit_profiles;
+            }
         }
 
         Ok(())
     }
 }
+
+/// Returns the path of the template file of the current repository
+///
+/// Outside a git working tree, the file is looked up in the current directory.
+pub fn local_template_path() -> PathBuf {
+    match GitCommands::toplevel() {
+        Ok(toplevel) => toplevel.join(LOCAL_TEMPLATE_FILE),
+        Err(_) => PathBuf::from(LOCAL_TEMPLATE_FILE),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(commit_types: &[&str], max_length: usize) -> LocalTemplate {
+        LocalTemplate {
+            commit_types: commit_types.iter().map(|t| t.to_string()).collect(),
+            output_format: CommitTemplate {
+                template: "<type>[optional scope]: <description>".to_string(),
+                max_length,
+                examples: Vec::new(),
+            },
+            audit_profiles: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn validate_message_accepts_conventional_subjects() {
+        let template = template(&["feat", "fix"], 50);
+        assert!(template.validate_message("feat: add login").is_ok());
+        assert!(template
+            .validate_message("fix(auth): expire tokens")
+            .is_ok());
+        assert!(template.validate_message("feat!: drop the v1 API").is_ok());
+        assert!(template
+            .validate_message("fix(api)!: reject empty bodies\n\nBody line")
+            .is_ok());
+    }
+
+    #[test]
+    fn validate_message_rejects_invalid_subjects() {
+        let template = template(&["feat", "fix"], 20);
+        assert_eq!(
+            template.validate_message("  \n"),
+            Err("commit message is empty".to_string())
+        );
+        assert_eq!(
+            template.validate_message("update things"),
+            Err("subject has no commit type".to_string())
+        );
+        assert_eq!(
+            template.validate_message("docs: readme"),
+            Err("unknown commit type 'docs'".to_string())
+        );
+        assert_eq!(
+            template.validate_message("fix(api: typo"),
+            Err("malformed scope in 'fix(api'".to_string())
+        );
+        assert_eq!(
+            template.validate_message("feat: a subject that is too long"),
+            Err("subject is longer than 20 characters".to_string())
+        );
+    }
+
+    #[test]
+    fn validate_message_ignores_the_prefix() {
+        let template = template(&["feat", "fix"], 20);
+        assert!(template
+            .validate_message("[JIRA-42] feat: add login")
+            .is_ok());
+        // Only the subject after the prefix counts towards the length
+        assert!(template
+            .validate_message("[JIRA-42] fix: exactly 20 char")
+            .is_ok());
+        assert_eq!(
+            template.validate_message("[JIRA-42] docs: readme"),
+            Err("unknown commit type 'docs'".to_string())
+        );
+        assert_eq!(
+            template.validate_message("[JIRA-42] "),
+            Err("commit message is empty".to_string())
+        );
+    }
+
+    #[test]
+    fn validate_message_counts_characters() {
+        let template = template(&["feat"], 12);
+        assert!(template.validate_message("feat: éàüöç").is_ok());
+    }
+
+    #[test]
+    fn validate_message_skips_disabled_checks() {
+        let template = template(&[], 0);
+        assert!(template
+            .validate_message("anything goes, at any length")
+            .is_ok());
+    }
+}
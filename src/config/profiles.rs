@@ -0,0 +1,134 @@
+use super::model::AuditProfile;
+
+/// Names of the built-in audit profiles, besides `general` which uses `audit.prompt`
+pub const BUILTIN_PROFILES: &[&str] = &[
+    "security",
+    "performance",
+    "correctness",
+    "test_coverage",
+    "api_compatibility",
+];
+
+/// Response format shared by the built-in prompts
+///
+/// The issues are wrapped in an object, as in the schema requested from
+/// providers supporting structured output, so both modes ask for the same shape.
+const RESPONSE_FORMAT: &str = r#"
+Return ONLY a JSON object, without markdown, with one object per issue in its "issues" array:
+{
+  "issues": [
+    {
+      "severity": "CRITICAL" or "HIGH" or "MEDIUM" or "LOW",
+      "title": "Brief issue title",
+      "impact": "Specific consequences of the issue",
+      "context": "Relevant code location or pattern",
+      "suggestion": "Concrete steps to resolve the issue"
+    }
+  ]
+}
+Return an empty "issues" array if you find no issue within this focus."#;
+
+/// Returns the prompt of a built-in profile
+///
+/// # Arguments
+/// * `name` - Name of the profile
+///
+/// # Returns
+/// The prompt template, with `{{git_diff}}` in place of the diff, or `None`
+/// if no built-in profile has this name
+fn builtin_prompt(name: &str) -> Option<String> {
+    let (role, focus, severities) = match name {
+        "security" => (
+            "a security auditor",
+            "- Secrets and credentials committed in code\n\
+             - Injection (SQL, command, path traversal, XSS)\n\
+             - Missing or broken authentication and authorization\n\
+             - Unsafe deserialization, cryptography misuse, disabled TLS verification\n\
+             - Sensitive data exposed in logs, errors or responses",
+            "- CRITICAL: Exploitable vulnerability or leaked secret\n\
+             - HIGH: Vulnerability requiring specific conditions\n\
+             - MEDIUM: Weakness that eases an attack\n\
+             - LOW: Hardening opportunity",
+        ),
+        "performance" => (
+            "a performance engineer",
+            "- Algorithmic complexity and work repeated in loops\n\
+             - Unnecessary allocations, copies and conversions\n\
+             - Blocking calls in async code, lock contention\n\
+             - N+1 queries, missing batching or caching\n\
+             - Unbounded memory growth and resource leaks",
+            "- CRITICAL: Change that makes the program unusable under normal load\n\
+             - HIGH: Regression noticeable on common paths\n\
+             - MEDIUM: Avoidable cost on less frequent paths\n\
+             - LOW: Minor optimization",
+        ),
+        "correctness" => (
+            "a meticulous code reviewer",
+            "- Logic errors, off-by-one errors and wrong conditions\n\
+             - Unhandled errors, panics and edge cases (empty, null, overflow)\n\
+             - Race conditions and inconsistent state\n\
+             - Behavior that contradicts names, comments or documentation",
+            "- CRITICAL: Data loss, corruption or crash on common paths\n\
+             - HIGH: Wrong results in realistic cases\n\
+             - MEDIUM: Wrong results in edge cases\n\
+             - LOW: Fragile code likely to break later",
+        ),
+        "test_coverage" => (
+            "a test engineer",
+            "- New or changed behavior without tests\n\
+             - Tests that do not assert what they claim to check\n\
+             - Missing edge cases and error paths in tests\n\
+             - Tests removed or weakened by the change",
+            "- CRITICAL: Tests removed or disabled without replacement\n\
+             - HIGH: Significant new behavior without any test\n\
+             - MEDIUM: Untested edge cases or error paths\n\
+             - LOW: Test readability or structure",
+        ),
+        "api_compatibility" => (
+            "a maintainer of a public API",
+            "- Removed or renamed public items, changed signatures\n\
+             - Changed defaults, return values or error behavior\n\
+             - Changed serialized formats, configuration keys or CLI options\n\
+             - Breaking changes missing a deprecation path or changelog entry",
+            "- CRITICAL: Breaking change to a stable API without notice\n\
+             - HIGH: Breaking change likely to affect users\n\
+             - MEDIUM: Subtle behavior change of a public item\n\
+             - LOW: Inconsistency with the existing API",
+        ),
+        _ => return None,
+    };
+
+    Some(format!(
+        "You are {}. Analyze the following git diff and report only the issues \
+         within this focus:\n{}\n\n<git_diff>\n{{{{git_diff}}}}\n</git_diff>\n\n\
+         Severity levels:\n{}\n{}",
+        role, focus, severities, RESPONSE_FORMAT
+    ))
+}
+
+/// Returns a built-in profile
+///
+/// # Arguments
+/// * `name` - Name of the profile
+///
+/// # Returns
+/// The profile with its default severities and gate, or `None` if no
+/// built-in profile has this name
+pub fn builtin_profile(name: &str) -> Option<AuditProfile> {
+    builtin_prompt(name).map(AuditProfile::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_prompts_ask_for_the_structured_shape() {
+        for name in BUILTIN_PROFILES {
+            let prompt = builtin_prompt(name).unwrap();
+            assert!(prompt.contains("{{git_diff}}"), "{}", name);
+            assert!(prompt.contains("\"issues\": ["), "{}", name);
+            assert!(!prompt.contains("JSON array"), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,428 @@
+use super::report::{Check, DoctorReport};
+use crate::config::{
+    expand_home, local_template_path, read_config, Config, GenerationParams, ProviderEntry,
+    ANTHROPIC_API_KEY_VAR, OPENAI_API_KEY_VAR,
+};
+use crate::git::GitCommands;
+use crate::providers::{build_provider, CompletionRequest, ProviderError};
+use crate::utils::Logger;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Prompt of the test request sent to each provider
+const TEST_PROMPT: &str = "Reply with the single word OK.";
+
+/// Tokens generated by the test request, enough for a short answer
+const TEST_MAX_TOKENS: u32 = 16;
+
+/// Models listed in the fix of an unavailable model
+const SUGGESTED_MODELS: usize = 8;
+
+/// Runs the diagnostic checks
+///
+/// Checks keep going after a failure, so the report lists every problem at
+/// once; checks depending on a failed one are skipped.
+///
+/// # Arguments
+/// * `config_path` - Path of the configuration file, as given on the command line
+///
+/// # Returns
+/// The result of every check, in order
+pub async fn run_checks(config_path: &str) -> DoctorReport {
+    let mut report = DoctorReport::default();
+
+    let config = check_config(config_path, &mut report);
+    let configured = match &config {
+        Some((config, path)) => check_settings(config, path, &mut report),
+        None => false,
+    };
+    check_git(&mut report);
+    if let Some((config, _)) = config {
+        check_template(config.clone(), &mut report);
+        if configured {
+            check_providers(&config, &mut report).await;
+        }
+    }
+    check_log_directory(&mut report);
+    report
+}
+
+/// Checks that the configuration file exists and parses
+///
+/// # Returns
+/// The configuration and the path it was read from, if it could be parsed
+fn check_config(config_path: &str, report: &mut DoctorReport) -> Option<(Config, PathBuf)> {
+    let path = match expand_home(Path::new(config_path)) {
+        Ok(path) => path,
+        Err(e) => {
+            report.add(Check::fail(
+                "Config file",
+                e.to_string(),
+                "Set HOME or pass an absolute path with --config",
+            ));
+            return None;
+        }
+    };
+    if !path.exists() {
+        report.add(Check::fail(
+            "Config file",
+            format!("{} not found", path.display()),
+            "Run `comgen init` to create it, or pass --config",
+        ));
+        return None;
+    }
+    report.add(Check::pass("Config file", path.display().to_string()));
+
+    match read_config(config_path) {
+        Ok(config) => {
+            report.add(Check::pass(
+                "Config",
+                format!("provider {} with model {}", config.provider, config.model),
+            ));
+            Some((config, path))
+        }
+        Err(e) => {
+            report.add(Check::fail(
+                "Config",
+                e.to_string(),
+                format!(
+                    "Fix the YAML syntax or the reported key in {}",
+                    path.display()
+                ),
+            ));
+            None
+        }
+    }
+}
+
+/// Checks the credentials of every provider of the chain, then the other settings
+///
+/// # Returns
+/// Whether the providers can be created and requested
+fn check_settings(config: &Config, path: &Path, report: &mut DoctorReport) -> bool {
+    let mut credentials = true;
+    for entry in config.provider_chain() {
+        let label = format!("{}:{}", entry.provider, entry.model);
+        match config.validate_provider(&entry) {
+            Ok(()) => report.add(Check::pass(
+                "Credentials",
+                format!("{} {}", label, configured_as(&entry, config)),
+            )),
+            Err(e) => {
+                credentials = false;
+                report.add(Check::fail(
+                    "Credentials",
+                    format!("{}: {}", label, e),
+                    credentials_fix(&entry, path),
+                ));
+            }
+        }
+    }
+    if !credentials {
+        return false;
+    }
+
+    let audit = &config.audit;
+    let validation = config.validate().and_then(|()| {
+        if audit.enabled && audit.llm {
+            audit.validate_profiles()
+        } else {
+            Ok(())
+        }
+    });
+    match validation {
+        Ok(()) => {
+            report.add(Check::pass("Settings", "valid"));
+            true
+        }
+        Err(e) => {
+            report.add(Check::fail(
+                "Settings",
+                e,
+                format!("Fix the reported setting in {}", path.display()),
+            ));
+            false
+        }
+    }
+}
+
+/// Describes how a provider of the chain is configured
+fn configured_as(entry: &ProviderEntry, config: &Config) -> String {
+    match entry.provider.as_str() {
+        "ollama" => format!("at {}", entry.url.as_deref().unwrap_or_default()),
+        "command" => format!("runs {}", config.command.program),
+        _ => "has an API key".to_string(),
+    }
+}
+
+/// Returns how to provide the missing setting of a provider
+fn credentials_fix(entry: &ProviderEntry, path: &Path) -> String {
+    let setting = match entry.provider.as_str() {
+        "openai" => "openai_api_key, or api_key for a fallback provider,",
+        "anthropic" => "anthropic_api_key, or api_key for a fallback provider,",
+        "ollama" => "ollama_url, or url for a fallback provider,",
+        "command" => "command.program",
+        _ => return "Use openai, anthropic, ollama or command as provider".to_string(),
+    };
+    let variable = match entry.provider.as_str() {
+        "openai" => format!("Export {}, or set", OPENAI_API_KEY_VAR),
+        "anthropic" => format!("Export {}, or set", ANTHROPIC_API_KEY_VAR),
+        _ => "Set".to_string(),
+    };
+    format!("{} {} in {}", variable, setting, path.display())
+}
+
+/// Checks that git runs, the current directory is in a repository and no
+/// operation prevents committing
+fn check_git(report: &mut DoctorReport) {
+    match GitCommands::version() {
+        Ok(version) => report.add(Check::pass("Git", version)),
+        Err(e) => {
+            report.add(Check::fail(
+                "Git",
+                e.to_string(),
+                "Install git and make sure it is in PATH",
+            ));
+            return;
+        }
+    }
+
+    match GitCommands::toplevel() {
+        Ok(toplevel) => report.add(Check::pass("Repository", toplevel.display().to_string())),
+        Err(e) => {
+            report.add(Check::fail(
+                "Repository",
+                e.to_string(),
+                "Run comgen from inside a git working tree",
+            ));
+            return;
+        }
+    }
+
+    match GitCommands::operation_in_progress() {
+        Ok(Some("bisect")) => report.add(Check::warn(
+            "Repository state",
+            "a bisect is in progress",
+            "End it with `git bisect reset` before committing",
+        )),
+        Ok(Some(operation)) => report.add(Check::warn(
+            "Repository state",
+            format!("a {} is in progress", operation),
+            format!(
+                "Finish it with `git {0} --continue` or abort it with `git {0} --abort`",
+                operation
+            ),
+        )),
+        Ok(None) => match GitCommands::current_branch() {
+            Ok(Some(branch)) => {
+                let modified = GitCommands::get_modified_files()
+                    .map(|files| files.len())
+                    .unwrap_or_default();
+                report.add(Check::pass(
+                    "Repository state",
+                    format!("on branch {}, {} modified files", branch, modified),
+                ));
+            }
+            Ok(None) => report.add(Check::warn(
+                "Repository state",
+                "HEAD is detached",
+                "Check out a branch first, commits on a detached HEAD are easily lost",
+            )),
+            Err(e) => report.add(Check::fail(
+                "Repository state",
+                e.to_string(),
+                "Run `git status` to inspect the repository",
+            )),
+        },
+        Err(e) => report.add(Check::fail(
+            "Repository state",
+            e.to_string(),
+            "Run `git status` to inspect the repository",
+        )),
+    }
+}
+
+/// Checks which commit template applies in the current directory
+fn check_template(mut config: Config, report: &mut DoctorReport) {
+    let local = local_template_path().exists();
+    if let Err(e) = config.load_local_template() {
+        report.add(Check::fail(
+            "Template",
+            format!("comgen.template: {}", e),
+            "Fix comgen.template or remove it to use the templates of the config file",
+        ));
+        return;
+    }
+
+    let source = if local {
+        "comgen.template"
+    } else {
+        "config file"
+    };
+    let templates = &config.templates;
+    if templates.commit_types.is_empty() {
+        report.add(Check::warn(
+            "Template",
+            format!("{} lists no commit types", source),
+            "List the allowed commit types under commit_types",
+        ));
+    } else {
+        report.add(Check::pass(
+            "Template",
+            format!(
+                "{} ({} commit types, {} characters max)",
+                source,
+                templates.commit_types.len(),
+                templates.output_format.max_length
+            ),
+        ));
+    }
+}
+
+/// Checks that every provider of the chain serves its model and answers a
+/// test request
+///
+/// Requests are not retried, so an unreachable provider fails fast.
+async fn check_providers(config: &Config, report: &mut DoctorReport) {
+    let mut config = config.clone();
+    config.request.max_retries = 0;
+
+    let mut entries = config.provider_chain();
+    let audit = &config.audit;
+    if let Some(model) = audit.model.as_deref() {
+        if audit.enabled && audit.llm && model != config.model {
+            entries.extend(config.provider_chain_with_model(model).into_iter().take(1));
+        }
+    }
+
+    let params = GenerationParams {
+        max_tokens: Some(TEST_MAX_TOKENS),
+        ..Default::default()
+    };
+    for entry in entries {
+        let label = format!("{}:{}", entry.provider, entry.model);
+        let provider = build_provider(&entry, &config);
+
+        match provider.models().await {
+            Ok(Some(models)) if !is_available(&models, &entry.model) => {
+                report.add(Check::fail(
+                    "Model",
+                    format!("{} does not serve {}", entry.provider, entry.model),
+                    model_fix(&entry, &models),
+                ));
+                continue;
+            }
+            Ok(Some(_)) => report.add(Check::pass("Model", format!("{} is available", label))),
+            Ok(None) => {}
+            Err(e) => {
+                report.add(Check::fail(
+                    "Connection",
+                    format!("{}: {}", label, e),
+                    connection_fix(&e, &entry),
+                ));
+                continue;
+            }
+        }
+
+        let start = Instant::now();
+        match provider
+            .generate_response(&CompletionRequest::new(TEST_PROMPT, &params))
+            .await
+        {
+            Ok(_) => report.add(Check::pass(
+                "Connection",
+                format!("{} answered in {} ms", label, start.elapsed().as_millis()),
+            )),
+            Err(e) => report.add(Check::fail(
+                "Connection",
+                format!("{}: {}", label, e),
+                connection_fix(&e, &entry),
+            )),
+        }
+    }
+}
+
+/// Checks whether a model is in the list of a provider
+///
+/// Ollama lists models with their tag, `:latest` being implied when omitted.
+fn is_available(models: &[String], model: &str) -> bool {
+    models
+        .iter()
+        .any(|available| available == model || available.strip_suffix(":latest") == Some(model))
+}
+
+/// Returns how to get a model the provider does not serve
+fn model_fix(entry: &ProviderEntry, models: &[String]) -> String {
+    if entry.provider == "ollama" {
+        return format!("Run `ollama pull {}`", entry.model);
+    }
+    let mut suggestions: Vec<&str> = models
+        .iter()
+        .take(SUGGESTED_MODELS)
+        .map(String::as_str)
+        .collect();
+    if models.len() > SUGGESTED_MODELS {
+        suggestions.push("...");
+    }
+    format!("Use one of: {}", suggestions.join(", "))
+}
+
+/// Returns how to address a failed request to a provider
+fn connection_fix(error: &ProviderError, entry: &ProviderEntry) -> String {
+    match error {
+        ProviderError::Auth { .. } => {
+            format!(
+                "Check the {} API key and the permissions of its account",
+                entry.provider
+            )
+        }
+        ProviderError::Network(_) | ProviderError::Timeout if entry.provider == "ollama" => {
+            format!(
+                "Start Ollama with `ollama serve`, or point ollama_url to it (currently {})",
+                entry.url.as_deref().unwrap_or_default()
+            )
+        }
+        ProviderError::Network(_) | ProviderError::Timeout => {
+            "Check the network connection and proxy settings".to_string()
+        }
+        ProviderError::RateLimit { .. } => {
+            "Wait for the rate limit to reset, or check the plan of the account".to_string()
+        }
+        ProviderError::Request { .. } => {
+            "Check the model name and the generation parameters".to_string()
+        }
+        ProviderError::Command(_) => "Check command.program and command.args".to_string(),
+        _ => "Retry later, the provider may be unavailable".to_string(),
+    }
+}
+
+/// Checks that log files can be written
+fn check_log_directory(report: &mut DoctorReport) {
+    let directory = match Logger::directory() {
+        Ok(directory) => directory,
+        Err(e) => {
+            report.add(Check::fail(
+                "Log directory",
+                e.to_string(),
+                "Set HOME to the home directory",
+            ));
+            return;
+        }
+    };
+
+    let probe = directory.join(".comgen-doctor");
+    let writable = std::fs::create_dir_all(&directory)
+        .and_then(|()| std::fs::write(&probe, b""))
+        .and_then(|()| std::fs::remove_file(&probe));
+    match writable {
+        Ok(()) => report.add(Check::pass(
+            "Log directory",
+            format!("{} is writable", directory.display()),
+        )),
+        Err(e) => report.add(Check::fail(
+            "Log directory",
+            format!("{}: {}", directory.display(), e),
+            format!("Make {} writable by the current user", directory.display()),
+        )),
+    }
+}
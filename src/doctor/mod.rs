@@ -0,0 +1,5 @@
+mod checks;
+mod report;
+
+pub use checks::run_checks;
+pub use report::{CheckStatus, DoctorReport};
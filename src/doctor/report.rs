@@ -0,0 +1,94 @@
+/// Outcome of a diagnostic check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// Everything is in order
+    Pass,
+    /// comgen works, possibly not as intended
+    Warn,
+    /// comgen cannot work until this is fixed
+    Fail,
+}
+
+/// Result of a diagnostic check
+#[derive(Debug, Clone)]
+pub struct Check {
+    /// What was checked
+    pub name: String,
+    /// Outcome of the check
+    pub status: CheckStatus,
+    /// What was found
+    pub detail: String,
+    /// How to fix a warning or failure
+    pub fix: Option<String>,
+}
+
+impl Check {
+    /// Creates a passed check
+    ///
+    /// # Arguments
+    /// * `name` - What was checked
+    /// * `detail` - What was found
+    pub fn pass(name: &str, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    /// Creates a check passed with a warning
+    ///
+    /// # Arguments
+    /// * `name` - What was checked
+    /// * `detail` - What was found
+    /// * `fix` - How to address the warning
+    pub fn warn(name: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    /// Creates a failed check
+    ///
+    /// # Arguments
+    /// * `name` - What was checked
+    /// * `detail` - What went wrong
+    /// * `fix` - How to fix it
+    pub fn fail(name: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Checks run by `comgen doctor`, in order
+#[derive(Debug, Default)]
+pub struct DoctorReport {
+    /// Results of the checks
+    pub checks: Vec<Check>,
+}
+
+impl DoctorReport {
+    /// Adds the result of a check
+    ///
+    /// # Arguments
+    /// * `check` - The result to add
+    pub fn add(&mut self, check: Check) {
+        self.checks.push(check);
+    }
+
+    /// Counts the failed checks
+    pub fn failures(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Fail)
+            .count()
+    }
+}
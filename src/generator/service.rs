@@ -0,0 +1,254 @@
+use crate::cache::ResponseCache;
+use crate::config::{GenerationConfig, GenerationParams};
+use crate::providers::{
+    AIProvider, Completion, CompletionRequest, OutputSchema, ProviderError, TokenSink,
+};
+use crate::usage::UsageTracker;
+use std::sync::Arc;
+
+/// Kind of request made by the application
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Task {
+    /// Generating a commit message
+    Commit,
+    /// Auditing a diff
+    Audit,
+}
+
+/// Provider and parameters answering the requests of a task
+struct Route {
+    /// The provider, or provider chain, answering requests
+    provider: Arc<dyn AIProvider>,
+    /// Task parameters, overriding the provider's own parameters
+    params: GenerationParams,
+}
+
+/// Entry point for every LLM request made while processing files
+///
+/// Routes each task to its provider and parameters, wraps them with the
+/// response cache, so identical requests are answered from disk instead of
+/// being paid for again, and records the tokens consumed by the requests
+/// that are actually sent.
+pub struct Generator {
+    /// Provider and parameters used for commit messages
+    commit: Route,
+    /// Provider and parameters used for audits
+    audit: Route,
+    /// Cache of previous responses
+    cache: ResponseCache,
+    /// Token usage and budget of the run
+    usage: UsageTracker,
+}
+
+impl Generator {
+    /// Creates a new generator
+    ///
+    /// # Arguments
+    /// * `provider` - The provider answering commit message requests
+    /// * `audit_provider` - The provider answering audit requests
+    /// * `generation` - Generation parameters of each task
+    /// * `cache` - Cache of previous responses
+    /// * `usage` - Token usage and budget of the run
+    pub fn new(
+        provider: Arc<dyn AIProvider>,
+        audit_provider: Arc<dyn AIProvider>,
+        generation: &GenerationConfig,
+        cache: ResponseCache,
+        usage: UsageTracker,
+    ) -> Self {
+        Self {
+            commit: Route {
+                provider,
+                params: generation.commit.clone(),
+            },
+            audit: Route {
+                provider: audit_provider,
+                params: generation.audit.clone(),
+            },
+            cache,
+            usage,
+        }
+    }
+
+    /// Returns the provider and parameters of a task
+    fn route(&self, task: Task) -> &Route {
+        match task {
+            Task::Commit => &self.commit,
+            Task::Audit => &self.audit,
+        }
+    }
+
+    /// Returns the token usage recorded during the run
+    pub fn usage(&self) -> &UsageTracker {
+        &self.usage
+    }
+
+    /// Records the usage of a completion and stores it in the cache
+    ///
+    /// # Arguments
+    /// * `key` - The cache key of the request
+    /// * `completion` - The completion returned by the provider
+    fn complete(&self, key: &str, completion: Completion) -> String {
+        self.usage.record(&completion);
+        self.cache.put(key, &completion.text);
+        completion.text
+    }
+
+    /// Computes the cache key of a prompt sent for a task
+    ///
+    /// The key covers the models and parameters actually sent, and structured
+    /// and free-form responses to a prompt are cached separately.
+    fn cache_key(&self, task: Task, prompt: &str, schema: Option<&OutputSchema>) -> String {
+        let route = self.route(task);
+        let identity = route.provider.cache_identity(&route.params);
+        let schema = schema
+            .map(|schema| schema.schema.to_string())
+            .unwrap_or_default();
+        ResponseCache::key(&[&identity, &schema, prompt])
+    }
+
+    /// Generates a response, served from the cache when available
+    ///
+    /// # Arguments
+    /// * `task` - The task the request is made for
+    /// * `prompt` - The input text to send to the model
+    /// * `schema` - Schema of the expected JSON response, if any
+    ///
+    /// # Returns
+    /// * `Ok(String)` - The generated or cached response
+    /// * `Err(ProviderError)` - If the request fails
+    pub async fn generate(
+        &self,
+        task: Task,
+        prompt: &str,
+        schema: Option<&OutputSchema>,
+    ) -> Result<String, ProviderError> {
+        let key = self.cache_key(task, prompt, schema);
+        if let Some(response) = self.cache.get(&key) {
+            return Ok(response);
+        }
+
+        // Held until the response is recorded, so concurrent requests share the budget
+        let _reservation = self.usage.reserve()?;
+        let route = self.route(task);
+        let request = CompletionRequest::new(prompt, &route.params).with_schema(schema);
+        let completion = route.provider.generate_response(&request).await?;
+        Ok(self.complete(&key, completion))
+    }
+
+    /// Streams a response, served at once from the cache when available
+    ///
+    /// # Arguments
+    /// * `task` - The task the request is made for
+    /// * `prompt` - The input text to send to the model
+    /// * `on_token` - Callback receiving each piece of generated text
+    ///
+    /// # Returns
+    /// * `Ok(String)` - The generated or cached response
+    /// * `Err(ProviderError)` - If the request fails
+    pub async fn generate_stream(
+        &self,
+        task: Task,
+        prompt: &str,
+        on_token: TokenSink<'_>,
+    ) -> Result<String, ProviderError> {
+        if let Some(response) = self.cache.get(&self.cache_key(task, prompt, None)) {
+            on_token(&response);
+            return Ok(response);
+        }
+
+        self.regenerate_stream(task, prompt, on_token).await
+    }
+
+    /// Streams a fresh response, bypassing the cache and replacing its entry
+    ///
+    /// Used when the user rejects a response and asks for a new one.
+    ///
+    /// # Arguments
+    /// * `task` - The task the request is made for
+    /// * `prompt` - The input text to send to the model
+    /// * `on_token` - Callback receiving each piece of generated text
+    ///
+    /// # Returns
+    /// * `Ok(String)` - The generated response
+    /// * `Err(ProviderError)` - If the request fails
+    pub async fn regenerate_stream(
+        &self,
+        task: Task,
+        prompt: &str,
+        on_token: TokenSink<'_>,
+    ) -> Result<String, ProviderError> {
+        let _reservation = self.usage.reserve()?;
+        let route = self.route(task);
+        let request = CompletionRequest::new(prompt, &route.params);
+        let completion = route.provider.generate_stream(&request, on_token).await?;
+        Ok(self.complete(&self.cache_key(task, prompt, None), completion))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CacheConfig, RequestConfig};
+    use crate::providers::ollama::OllamaProvider;
+    use std::collections::HashMap;
+
+    fn params(temperature: f64) -> GenerationParams {
+        GenerationParams {
+            temperature: Some(temperature),
+            ..GenerationParams::default()
+        }
+    }
+
+    /// Creates a generator whose provider has its own parameters
+    fn generator(model: &str, provider: GenerationParams, task: GenerationParams) -> Generator {
+        let provider: Arc<dyn AIProvider> = Arc::new(OllamaProvider::new(
+            model,
+            "http://localhost:11434",
+            false,
+            provider,
+            &RequestConfig::default(),
+        ));
+        let generation = GenerationConfig {
+            commit: task,
+            ..GenerationConfig::default()
+        };
+        Generator::new(
+            Arc::clone(&provider),
+            provider,
+            &generation,
+            ResponseCache::new(&CacheConfig::default(), false).unwrap(),
+            UsageTracker::new(HashMap::new(), None),
+        )
+    }
+
+    fn key(generator: &Generator) -> String {
+        generator.cache_key(Task::Commit, "diff", None)
+    }
+
+    #[test]
+    fn cache_key_covers_provider_parameters() {
+        let task = GenerationParams::default();
+        assert_ne!(
+            key(&generator("llama3", params(0.2), task.clone())),
+            key(&generator("llama3", params(0.8), task))
+        );
+    }
+
+    #[test]
+    fn cache_key_covers_the_model() {
+        assert_ne!(
+            key(&generator("llama3", params(0.2), params(0.5))),
+            key(&generator("mistral", params(0.2), params(0.5)))
+        );
+    }
+
+    #[test]
+    fn cache_key_follows_the_parameters_sent() {
+        // The task overrides the provider's temperature, so the same request is sent
+        assert_eq!(
+            key(&generator("llama3", params(0.2), params(0.5))),
+            key(&generator("llama3", params(0.8), params(0.5)))
+        );
+    }
+}
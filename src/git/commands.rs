@@ -1,9 +1,47 @@
 use super::error::GitError;
+use std::path::PathBuf;
 use std::process::Command;
 
 /// Provides Git command functionality through system commands
 pub struct GitCommands;
 
+/// Set of changes to read from the repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffTarget {
+    /// Unstaged changes of the working tree
+    WorkingTree,
+    /// Changes staged for the next commit
+    Staged,
+    /// Changes introduced by a single commit, against its first parent
+    Commit(String),
+    /// Changes between two revisions, such as `main..HEAD`
+    Range(String),
+}
+
+impl DiffTarget {
+    /// Returns the git command and arguments listing or showing the changes
+    fn args(&self) -> Vec<&str> {
+        match self {
+            DiffTarget::WorkingTree => vec!["diff"],
+            DiffTarget::Staged => vec!["diff", "--cached"],
+            // `show` also handles root commits, which have no parent to diff against
+            DiffTarget::Commit(rev) => vec!["show", "--format=", "-m", "--first-parent", rev],
+            DiffTarget::Range(range) => vec!["diff", range],
+        }
+    }
+}
+
+/// A commit listed by [`GitCommands::get_log`]
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    /// Abbreviated hash of the commit
+    pub hash: String,
+    /// First line of the message
+    pub subject: String,
+    /// Rest of the message, trailers included
+    pub body: String,
+}
+
 impl GitCommands {
     /// Gets a list of modified files in the Git repository
     ///
@@ -77,6 +115,189 @@ impl GitCommands {
         Ok(String::from_utf8(output.stdout)?)
     }
 
+    /// Lists the files changed by a set of changes, deleted files excluded
+    ///
+    /// # Arguments
+    /// * `target` - The changes to inspect
+    ///
+    /// # Returns
+    /// * `Ok(Vec<String>)` - Paths of the changed files
+    /// * `Err(GitError)` - If Git command execution fails or a revision is unknown
+    pub fn get_changed_files(target: &DiffTarget) -> Result<Vec<String>, GitError> {
+        let output = Command::new("git")
+            .args(target.args())
+            .args(["--name-only", "--diff-filter=d"])
+            .output()?;
+
+        if !output.status.success() {
+            let error_msg = String::from_utf8_lossy(&output.stderr);
+            // Only the first line says what went wrong, the rest is usage hints
+            return Err(GitError::DiffError(format!(
+                "Failed to list changed files: {}",
+                error_msg.lines().next().unwrap_or_default()
+            )));
+        }
+
+        Ok(String::from_utf8(output.stdout)?
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect())
+    }
+
+    /// Gets the diff of a file for a set of changes
+    ///
+    /// # Arguments
+    /// * `target` - The changes to inspect
+    /// * `file` - Path of the file
+    ///
+    /// # Returns
+    /// * `Ok(String)` - The diff output
+    /// * `Err(GitError)` - If Git command execution fails
+    pub fn get_target_diff(target: &DiffTarget, file: &str) -> Result<String, GitError> {
+        let output = Command::new("git")
+            .args(target.args())
+            .args(["--no-color", "--no-ext-diff", "--", file])
+            .output()?;
+
+        if !output.status.success() {
+            let error_msg = String::from_utf8_lossy(&output.stderr);
+            return Err(GitError::DiffError(format!(
+                "Failed to get diff of {}: {}",
+                file,
+                error_msg.trim()
+            )));
+        }
+
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    /// Gets the diff of every change staged for commit
+    ///
+    /// # Returns
+    /// * `Ok(String)` - The diff output, empty if nothing is staged
+    /// * `Err(GitError)` - If Git command execution fails
+    pub fn get_staged_diff() -> Result<String, GitError> {
+        let output = Command::new("git")
+            .args(["diff", "--cached", "--no-color", "--no-ext-diff"])
+            .output()?;
+
+        if !output.status.success() {
+            let error_msg = String::from_utf8_lossy(&output.stderr);
+            return Err(GitError::DiffError(format!(
+                "Failed to get staged diff: {}",
+                error_msg.trim()
+            )));
+        }
+
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    /// Returns the version of the git executable
+    ///
+    /// # Returns
+    /// * `Ok(String)` - The version, such as `git version 2.39.5`
+    /// * `Err(GitError)` - If git cannot be run
+    pub fn version() -> Result<String, GitError> {
+        let output = Command::new("git").arg("--version").output()?;
+        Ok(String::from_utf8(output.stdout)?.trim().to_string())
+    }
+
+    /// Returns the top level of the working tree containing the current directory
+    ///
+    /// # Returns
+    /// * `Ok(PathBuf)` - The top level directory
+    /// * `Err(GitError)` - If the current directory is not in a git working tree
+    pub fn toplevel() -> Result<PathBuf, GitError> {
+        let output = Command::new("git")
+            .args(["rev-parse", "--show-toplevel"])
+            .output()?;
+        if !output.status.success() {
+            return Err(GitError::RepositoryError(
+                "Not inside a git working tree".to_string(),
+            ));
+        }
+        Ok(PathBuf::from(String::from_utf8(output.stdout)?.trim()))
+    }
+
+    /// Returns the branch checked out in the working tree
+    ///
+    /// # Returns
+    /// * `Ok(Some(String))` - The short name of the branch
+    /// * `Ok(None)` - If HEAD is detached
+    /// * `Err(GitError)` - If git cannot be run
+    pub fn current_branch() -> Result<Option<String>, GitError> {
+        let output = Command::new("git")
+            .args(["symbolic-ref", "--short", "-q", "HEAD"])
+            .output()?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        Ok(Some(String::from_utf8(output.stdout)?.trim().to_string()))
+    }
+
+    /// Returns the operation left in progress in the repository, if any
+    ///
+    /// # Returns
+    /// * `Ok(Some(&str))` - The operation, such as `merge` or `rebase`, to be
+    ///   continued or aborted before committing
+    /// * `Ok(None)` - If no operation is in progress
+    /// * `Err(GitError)` - If the current directory is not in a git repository
+    pub fn operation_in_progress() -> Result<Option<&'static str>, GitError> {
+        let toplevel = Self::toplevel()?;
+        let markers = [
+            ("rebase-merge", "rebase"),
+            ("rebase-apply", "rebase"),
+            ("MERGE_HEAD", "merge"),
+            ("CHERRY_PICK_HEAD", "cherry-pick"),
+            ("REVERT_HEAD", "revert"),
+            ("BISECT_LOG", "bisect"),
+        ];
+
+        for (marker, operation) in markers {
+            let output = Command::new("git")
+                .current_dir(&toplevel)
+                .args(["rev-parse", "--git-path", marker])
+                .output()?;
+            if !output.status.success() {
+                let error_msg = String::from_utf8_lossy(&output.stderr);
+                return Err(GitError::RepositoryError(error_msg.trim().to_string()));
+            }
+            if toplevel
+                .join(String::from_utf8(output.stdout)?.trim())
+                .exists()
+            {
+                return Ok(Some(operation));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the directory git runs hooks from, honoring `core.hooksPath`
+    ///
+    /// # Returns
+    /// * `Ok(PathBuf)` - The hooks directory, relative paths being resolved
+    ///   against the top level of the working tree
+    /// * `Err(GitError)` - If the current directory is not in a git repository
+    pub fn hooks_dir() -> Result<PathBuf, GitError> {
+        let toplevel = Self::toplevel()?;
+
+        let output = Command::new("git")
+            .current_dir(&toplevel)
+            .args(["rev-parse", "--git-path", "hooks"])
+            .output()?;
+        if !output.status.success() {
+            let error_msg = String::from_utf8_lossy(&output.stderr);
+            return Err(GitError::HooksError(format!(
+                "Failed to locate hooks directory: {}",
+                error_msg.trim()
+            )));
+        }
+
+        // Relative paths, including a relative `core.hooksPath`, are relative to the top level
+        Ok(toplevel.join(String::from_utf8(output.stdout)?.trim()))
+    }
+
     /// Stages a file for commit
     ///
     /// # Arguments
@@ -106,14 +327,18 @@ impl GitCommands {
     ///
     /// # Arguments
     /// * `message` - The commit message
+    /// * `trailers` - Trailers appended to the message, such as `Audited-by: comgen/gpt-4o`
     ///
     /// # Returns
     /// * `Ok(())` - If commit succeeds
     /// * `Err(GitError)` - If Git command execution fails
-    pub fn commit(message: &str) -> Result<(), GitError> {
-        let output = Command::new("git")
-            .args(["commit", "-q", "-m", message])
-            .output()?;
+    pub fn commit(message: &str, trailers: &[String]) -> Result<(), GitError> {
+        let mut command = Command::new("git");
+        command.args(["commit", "-q", "-m", message]);
+        for trailer in trailers {
+            command.args(["--trailer", trailer]);
+        }
+        let output = command.output()?;
 
         if !output.status.success() {
             let error_msg = String::from_utf8_lossy(&output.stderr);
@@ -133,6 +358,92 @@ impl GitCommands {
         Ok(())
     }
 
+    /// Returns the most recent tag reachable from `HEAD`
+    ///
+    /// # Returns
+    /// * `Ok(Some(String))` - The tag name
+    /// * `Ok(None)` - If no tag is reachable
+    /// * `Err(GitError)` - If Git command execution fails
+    pub fn latest_tag() -> Result<Option<String>, GitError> {
+        let output = Command::new("git")
+            .args(["describe", "--tags", "--abbrev=0"])
+            .output()?;
+
+        // git describe fails when there is no tag to describe from
+        if !output.status.success() {
+            return Ok(None);
+        }
+        let tag = String::from_utf8(output.stdout)?.trim().to_string();
+        Ok((!tag.is_empty()).then_some(tag))
+    }
+
+    /// Lists the commits of a revision range, merges excluded, newest first
+    ///
+    /// # Arguments
+    /// * `range` - Revision range, such as `v1.2.0..HEAD`
+    /// * `max_count` - Maximum number of commits listed, if any
+    ///
+    /// # Returns
+    /// * `Ok(Vec<LogEntry>)` - The commits of the range
+    /// * `Err(GitError)` - If Git command execution fails or the range is invalid
+    pub fn get_log(range: &str, max_count: Option<usize>) -> Result<Vec<LogEntry>, GitError> {
+        let mut command = Command::new("git");
+        // Fields are separated by US and commits by RS, which messages do not contain
+        command.args(["log", "--no-merges", "--format=%h%x1f%s%x1f%b%x1e"]);
+        if let Some(max_count) = max_count {
+            command.arg(format!("--max-count={}", max_count));
+        }
+        let output = command.args([range, "--"]).output()?;
+
+        if !output.status.success() {
+            let error_msg = String::from_utf8_lossy(&output.stderr);
+            return Err(GitError::LogError(format!(
+                "Failed to list commits of {}: {}",
+                range,
+                error_msg.lines().next().unwrap_or_default()
+            )));
+        }
+
+        Ok(String::from_utf8(output.stdout)?
+            .split('\x1e')
+            .filter_map(|record| {
+                let mut fields = record.trim_start_matches('\n').splitn(3, '\x1f');
+                Some(LogEntry {
+                    hash: fields.next()?.to_string(),
+                    subject: fields.next()?.to_string(),
+                    body: fields.next().unwrap_or_default().trim().to_string(),
+                })
+            })
+            .collect())
+    }
+
+    /// Attaches a note to a commit, replacing its previous note under the same ref
+    ///
+    /// # Arguments
+    /// * `notes_ref` - Notes ref, such as `comgen-audit` for `refs/notes/comgen-audit`
+    /// * `rev` - The annotated commit
+    /// * `content` - Content of the note
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the note was written
+    /// * `Err(GitError)` - If Git command execution fails
+    pub fn add_note(notes_ref: &str, rev: &str, content: &str) -> Result<(), GitError> {
+        let output = Command::new("git")
+            .arg("notes")
+            .arg(format!("--ref={}", notes_ref))
+            .args(["add", "-f", "-m", content, rev])
+            .output()?;
+
+        if !output.status.success() {
+            let error_msg = String::from_utf8_lossy(&output.stderr);
+            return Err(GitError::NotesError(format!(
+                "Git notes failed: {}",
+                error_msg.lines().next().unwrap_or_default()
+            )));
+        }
+        Ok(())
+    }
+
     /// Pushes commits to the remote repository
     ///
     /// # Returns
@@ -0,0 +1,279 @@
+/// Kind of a line in a diff hunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    /// Unchanged line shown for context
+    Context,
+    /// Line added in the new version
+    Added,
+    /// Line removed from the old version
+    Removed,
+}
+
+/// A single line of a diff hunk
+#[derive(Debug, Clone)]
+pub struct DiffLine {
+    /// Whether the line was added, removed or kept
+    pub kind: LineKind,
+    /// Line number in the new version of the file, `None` for removed lines
+    pub new_line: Option<u32>,
+    /// Content of the line, without the diff marker
+    pub content: String,
+}
+
+/// A hunk of a unified diff
+#[derive(Debug, Clone)]
+pub struct DiffHunk {
+    /// The `@@` header line of the hunk
+    pub header: String,
+    /// First line of the hunk in the new version of the file
+    pub new_start: u32,
+    /// Number of lines of the hunk in the new version of the file
+    pub new_count: u32,
+    /// Lines of the hunk, in order
+    pub lines: Vec<DiffLine>,
+}
+
+impl DiffHunk {
+    /// Checks whether a line of the new version of the file belongs to the hunk
+    ///
+    /// # Arguments
+    /// * `line` - Line number in the new version of the file
+    pub fn contains(&self, line: u32) -> bool {
+        line >= self.new_start && line < self.new_start + self.new_count
+    }
+}
+
+/// The diff of a single file, split in hunks
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    /// Path of the file
+    pub file: String,
+    /// Hunks of the diff, in order
+    pub hunks: Vec<DiffHunk>,
+}
+
+impl FileDiff {
+    /// Parses the diff of a file
+    ///
+    /// # Arguments
+    /// * `file` - Path of the file
+    /// * `diff` - The diff output for this file only
+    pub fn new(file: &str, diff: &str) -> Self {
+        Self {
+            file: file.to_string(),
+            hunks: parse_hunks(diff),
+        }
+    }
+}
+
+/// Parses the hunks of a unified diff, as printed by `git diff`
+///
+/// File headers are skipped, and lines are numbered from the `@@` hunk
+/// headers so findings can be mapped back to the new version of the file.
+///
+/// # Arguments
+/// * `diff` - The diff output
+///
+/// # Returns
+/// The hunks of the diff, in order
+pub fn parse_hunks(diff: &str) -> Vec<DiffHunk> {
+    let mut hunks: Vec<DiffHunk> = Vec::new();
+    let mut next_line = 0;
+    // Lines of the current hunk still expected on the old and new sides
+    let mut remaining_old = 0;
+    let mut remaining_new = 0;
+
+    for line in diff.lines() {
+        if let Some(header) = parse_hunk_header(line) {
+            next_line = header.new_start;
+            remaining_old = header.old_count;
+            remaining_new = header.new_count;
+            hunks.push(DiffHunk {
+                header: line.to_string(),
+                new_start: header.new_start,
+                new_count: header.new_count,
+                lines: Vec::new(),
+            });
+            continue;
+        }
+
+        // Lines after a complete hunk belong to the headers of the next file
+        let Some(hunk) = hunks.last_mut() else {
+            continue;
+        };
+        if remaining_old == 0 && remaining_new == 0 {
+            continue;
+        }
+
+        let (kind, content) = match line.chars().next() {
+            Some('+') => (LineKind::Added, &line[1..]),
+            Some('-') => (LineKind::Removed, &line[1..]),
+            Some(' ') => (LineKind::Context, &line[1..]),
+            // Blank context lines may lose their leading space
+            None => (LineKind::Context, ""),
+            // "\ No newline at end of file"
+            _ => continue,
+        };
+
+        let new_line = if kind == LineKind::Removed {
+            None
+        } else {
+            Some(next_line)
+        };
+        if kind != LineKind::Added {
+            remaining_old = remaining_old.saturating_sub(1);
+        }
+        if kind != LineKind::Removed {
+            remaining_new = remaining_new.saturating_sub(1);
+            next_line += 1;
+        }
+
+        hunk.lines.push(DiffLine {
+            kind,
+            new_line,
+            content: content.to_string(),
+        });
+    }
+    hunks
+}
+
+/// Formats hunks with the new-file line number in front of each line
+///
+/// Removed lines have no number in the new version of the file and are
+/// left unnumbered, so a model can refer to findings by line.
+///
+/// # Arguments
+/// * `hunks` - The hunks to format
+///
+/// # Returns
+/// The numbered diff, one hunk after another
+pub fn format_numbered(hunks: &[DiffHunk]) -> String {
+    let mut output = String::new();
+    for hunk in hunks {
+        output.push_str(&hunk.header);
+        output.push('\n');
+        for line in &hunk.lines {
+            let number = line.new_line.map(|n| n.to_string()).unwrap_or_default();
+            let marker = match line.kind {
+                LineKind::Context => ' ',
+                LineKind::Added => '+',
+                LineKind::Removed => '-',
+            };
+            output.push_str(&format!("{:>5} {}{}\n", number, marker, line.content));
+        }
+    }
+    output
+}
+
+/// Line ranges of a hunk header
+struct HunkHeader {
+    /// Number of lines of the hunk in the old version of the file
+    old_count: u32,
+    /// First line of the hunk in the new version of the file
+    new_start: u32,
+    /// Number of lines of the hunk in the new version of the file
+    new_count: u32,
+}
+
+/// Parses a hunk header such as `@@ -10,7 +12,8 @@ fn main()`
+fn parse_hunk_header(line: &str) -> Option<HunkHeader> {
+    let mut ranges = line.strip_prefix("@@ -")?.split(' ');
+    let (_, old_count) = parse_range(ranges.next()?)?;
+    let (new_start, new_count) = parse_range(ranges.next()?.strip_prefix('+')?)?;
+    Some(HunkHeader {
+        old_count,
+        new_start,
+        new_count,
+    })
+}
+
+/// Parses a `start,count` range, the count defaulting to one
+fn parse_range(range: &str) -> Option<(u32, u32)> {
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -10,4 +10,5 @@ fn main() {
+ let a = 1;
+-let b = 2;
++let b = 3;
++let c = 4;
+
+ println!(\"{}\", a);
+@@ -40 +41,2 @@
+ last();
++extra();
+\\ No newline at end of file
+";
+
+    #[test]
+    fn parse_hunks_numbers_new_lines() {
+        let hunks = parse_hunks(DIFF);
+        assert_eq!(hunks.len(), 2);
+
+        let first = &hunks[0];
+        assert_eq!((first.new_start, first.new_count), (10, 5));
+        let numbers: Vec<_> = first.lines.iter().map(|line| line.new_line).collect();
+        assert_eq!(
+            numbers,
+            [Some(10), None, Some(11), Some(12), Some(13), Some(14)]
+        );
+        assert_eq!(first.lines[1].kind, LineKind::Removed);
+        assert_eq!(first.lines[2].content, "let b = 3;");
+        // The blank context line lost its leading space
+        assert_eq!(first.lines[4].kind, LineKind::Context);
+        assert_eq!(first.lines[4].content, "");
+    }
+
+    #[test]
+    fn parse_hunks_defaults_counts_and_skips_markers() {
+        let hunks = parse_hunks(DIFF);
+        let second = &hunks[1];
+        assert_eq!((second.new_start, second.new_count), (41, 2));
+        assert_eq!(second.lines.len(), 2);
+        assert_eq!(second.lines[1].new_line, Some(42));
+        assert!(second.contains(42));
+        assert!(!second.contains(43));
+    }
+
+    #[test]
+    fn parse_hunks_skips_headers_of_following_files() {
+        let diff = "\
+@@ -1 +1 @@
+-old
++new
+diff --git a/b.rs b/b.rs
+--- a/b.rs
++++ b/b.rs
+@@ -1,0 +1,1 @@
++added
+";
+        let hunks = parse_hunks(diff);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0].lines.len(), 2);
+        assert_eq!(hunks[1].lines.len(), 1);
+        assert_eq!(hunks[1].lines[0].content, "added");
+    }
+
+    #[test]
+    fn format_numbered_leaves_removed_lines_unnumbered() {
+        let hunks = parse_hunks(DIFF);
+        let numbered = format_numbered(&hunks[..1]);
+        let lines: Vec<_> = numbered.lines().collect();
+        assert_eq!(lines[0], "@@ -10,4 +10,5 @@ fn main() {");
+        assert_eq!(lines[1], "   10  let a = 1;");
+        assert_eq!(lines[2], "      -let b = 2;");
+        assert_eq!(lines[3], "   11 +let b = 3;");
+    }
+}
@@ -2,6 +2,7 @@ use thiserror::Error;
 
 /// Represents errors that can occur during Git operations
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum GitError {
     /// Error that occurs during git diff command execution
     #[error("Git diff error: {0}")]
@@ -19,6 +20,22 @@ pub enum GitError {
     #[error("Git push error: {0}")]
     PushError(String),
 
+    /// Error that occurs when a note cannot be attached to a commit
+    #[error("Git notes error: {0}")]
+    NotesError(String),
+
+    /// Error that occurs when the current directory is not in a repository
+    #[error("Git repository error: {0}")]
+    RepositoryError(String),
+
+    /// Error that occurs when the commits of a range cannot be listed
+    #[error("Git log error: {0}")]
+    LogError(String),
+
+    /// Error that occurs when the hooks directory cannot be located
+    #[error("Git hooks error: {0}")]
+    HooksError(String),
+
     /// Underlying IO error from std::io
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
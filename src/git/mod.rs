@@ -1,4 +1,6 @@
 mod commands;
+mod diff;
 mod error;
 
-pub use commands::GitCommands;
+pub use commands::{DiffTarget, GitCommands, LogEntry};
+pub use diff::{format_numbered, DiffHunk, DiffLine, FileDiff, LineKind};
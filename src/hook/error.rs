@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+/// Represents errors that can occur while installing or removing git hooks
+#[derive(Error, Debug)]
+pub enum HookError {
+    /// Error that occurs when a hook file cannot be read, written or moved
+    #[error("Hook IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Error that occurs when an existing hook cannot be chained safely
+    #[error("Hook conflict: {0}")]
+    Conflict(String),
+}
@@ -0,0 +1,321 @@
+use super::error::HookError;
+use clap::ValueEnum;
+use std::path::{Path, PathBuf};
+
+/// Line identifying the hooks written by comgen
+const MARKER: &str = "# Installed by comgen, remove with `comgen hook uninstall`";
+
+/// Suffix of an existing hook moved aside and run before comgen's
+const CHAINED_SUFFIX: &str = ".pre-comgen";
+
+/// Git hooks comgen integrates with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HookKind {
+    /// Fills the commit message from the staged diff
+    PrepareCommitMsg,
+    /// Checks the commit message against the template
+    CommitMsg,
+}
+
+impl HookKind {
+    /// Returns the file name git looks for in the hooks directory
+    pub fn file_name(self) -> &'static str {
+        match self {
+            HookKind::PrepareCommitMsg => "prepare-commit-msg",
+            HookKind::CommitMsg => "commit-msg",
+        }
+    }
+}
+
+/// Outcome of installing a hook
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstallOutcome {
+    /// No hook existed
+    Installed,
+    /// A previous comgen hook was replaced
+    Updated,
+    /// An existing hook was moved to this path and is run first
+    Chained(PathBuf),
+}
+
+/// Writes and removes the comgen hooks of a repository
+///
+/// An existing hook is never overwritten: it is moved aside and run before
+/// comgen, and restored on uninstall.
+pub struct HookInstaller {
+    /// Directory git runs hooks from
+    directory: PathBuf,
+}
+
+impl HookInstaller {
+    /// Creates an installer for a hooks directory
+    ///
+    /// # Arguments
+    /// * `directory` - Directory git runs hooks from, honoring `core.hooksPath`
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+
+    /// Installs a hook, chaining the existing one if it is not comgen's
+    ///
+    /// # Arguments
+    /// * `kind` - The hook to install
+    /// * `program` - Path of the comgen executable
+    /// * `config` - Configuration file the hook loads
+    ///
+    /// # Returns
+    /// * `Ok(InstallOutcome)` - How the hook was installed
+    /// * `Err(HookError)` - If the hook cannot be written, or an existing hook
+    ///   cannot be moved aside without overwriting a previously chained one
+    pub fn install(
+        &self,
+        kind: HookKind,
+        program: &str,
+        config: &str,
+    ) -> Result<InstallOutcome, HookError> {
+        std::fs::create_dir_all(&self.directory)?;
+        let path = self.path(kind);
+        let chained = self.chained_path(kind);
+
+        let outcome = if !path.exists() {
+            InstallOutcome::Installed
+        } else if is_comgen_hook(&path) {
+            InstallOutcome::Updated
+        } else if chained.exists() {
+            return Err(HookError::Conflict(format!(
+                "{} exists and {} is already taken, move one of them first",
+                path.display(),
+                chained.display()
+            )));
+        } else {
+            std::fs::rename(&path, &chained)?;
+            InstallOutcome::Chained(chained)
+        };
+
+        std::fs::write(&path, script(kind, program, config))?;
+        make_executable(&path)?;
+        Ok(outcome)
+    }
+
+    /// Removes a comgen hook, restoring the hook it chained
+    ///
+    /// # Arguments
+    /// * `kind` - The hook to remove
+    ///
+    /// # Returns
+    /// * `Ok(true)` - If the hook was removed
+    /// * `Ok(false)` - If no comgen hook was installed, other hooks being left alone
+    /// * `Err(HookError)` - If the hook cannot be removed or restored
+    pub fn uninstall(&self, kind: HookKind) -> Result<bool, HookError> {
+        let path = self.path(kind);
+        if !path.exists() || !is_comgen_hook(&path) {
+            return Ok(false);
+        }
+
+        std::fs::remove_file(&path)?;
+        let chained = self.chained_path(kind);
+        if chained.exists() {
+            std::fs::rename(&chained, &path)?;
+        }
+        Ok(true)
+    }
+
+    /// Returns the path of a hook
+    pub fn path(&self, kind: HookKind) -> PathBuf {
+        self.directory.join(kind.file_name())
+    }
+
+    /// Returns the path an existing hook is moved to when chained
+    fn chained_path(&self, kind: HookKind) -> PathBuf {
+        self.directory
+            .join(format!("{}{}", kind.file_name(), CHAINED_SUFFIX))
+    }
+}
+
+/// Builds the script of a hook
+///
+/// The chained hook runs first with the same arguments, and its failure
+/// aborts the commit as it would without comgen.
+///
+/// # Arguments
+/// * `kind` - The hook
+/// * `program` - Path of the comgen executable
+/// * `config` - Configuration file the hook loads
+fn script(kind: HookKind, program: &str, config: &str) -> String {
+    let name = kind.file_name();
+    format!(
+        "#!/bin/sh\n\
+         {marker}\n\
+         chained=\"$(dirname \"$0\")/{name}{suffix}\"\n\
+         if [ -x \"$chained\" ]; then\n    \
+             \"$chained\" \"$@\" || exit $?\n\
+         fi\n\
+         exec {program} --config {config} hook run {name} \"$@\"\n",
+        marker = MARKER,
+        name = name,
+        suffix = CHAINED_SUFFIX,
+        program = shell_quote(program),
+        config = shell_quote(config),
+    )
+}
+
+/// Checks whether a hook was written by comgen
+fn is_comgen_hook(path: &Path) -> bool {
+    std::fs::read_to_string(path)
+        .map(|content| content.contains(MARKER))
+        .unwrap_or(false)
+}
+
+/// Quotes a value for a POSIX shell
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Marks a hook as executable, as git ignores hooks that are not
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<(), HookError> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+/// Marks a hook as executable, which other platforms do not require
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<(), HookError> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hooks directory removed when the test ends
+    struct HooksDir(PathBuf);
+
+    impl HooksDir {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("comgen-hooks-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            Self(path)
+        }
+
+        fn installer(&self) -> HookInstaller {
+            HookInstaller::new(self.0.clone())
+        }
+
+        fn read(&self, name: &str) -> String {
+            std::fs::read_to_string(self.0.join(name)).unwrap()
+        }
+    }
+
+    impl Drop for HooksDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const EXISTING: &str = "#!/bin/sh\necho lint\n";
+
+    #[test]
+    fn install_writes_an_executable_hook() {
+        let dir = HooksDir::new("install");
+        let installer = dir.installer();
+        let outcome = installer
+            .install(
+                HookKind::CommitMsg,
+                "/usr/bin/comgen",
+                "~/.comgen/config.yaml",
+            )
+            .unwrap();
+
+        assert_eq!(outcome, InstallOutcome::Installed);
+        let script = dir.read("commit-msg");
+        assert!(script.contains(MARKER));
+        assert!(script.ends_with(
+            "exec '/usr/bin/comgen' --config '~/.comgen/config.yaml' hook run commit-msg \"$@\"\n"
+        ));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let path = installer.path(HookKind::CommitMsg);
+            let mode = std::fs::metadata(path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+    }
+
+    #[test]
+    fn install_chains_an_existing_hook_and_uninstall_restores_it() {
+        let dir = HooksDir::new("chain");
+        let installer = dir.installer();
+        std::fs::create_dir_all(&dir.0).unwrap();
+        std::fs::write(installer.path(HookKind::PrepareCommitMsg), EXISTING).unwrap();
+
+        let outcome = installer
+            .install(HookKind::PrepareCommitMsg, "comgen", "config.yaml")
+            .unwrap();
+        let chained = dir.0.join("prepare-commit-msg.pre-comgen");
+        assert_eq!(outcome, InstallOutcome::Chained(chained.clone()));
+        assert_eq!(dir.read("prepare-commit-msg.pre-comgen"), EXISTING);
+        assert!(dir.read("prepare-commit-msg").contains(MARKER));
+
+        assert!(installer.uninstall(HookKind::PrepareCommitMsg).unwrap());
+        assert_eq!(dir.read("prepare-commit-msg"), EXISTING);
+        assert!(!chained.exists());
+    }
+
+    #[test]
+    fn reinstall_updates_the_hook_in_place() {
+        let dir = HooksDir::new("reinstall");
+        let installer = dir.installer();
+        std::fs::create_dir_all(&dir.0).unwrap();
+        std::fs::write(installer.path(HookKind::CommitMsg), EXISTING).unwrap();
+
+        installer
+            .install(HookKind::CommitMsg, "comgen", "old.yaml")
+            .unwrap();
+        let outcome = installer
+            .install(HookKind::CommitMsg, "comgen", "new.yaml")
+            .unwrap();
+
+        assert_eq!(outcome, InstallOutcome::Updated);
+        assert!(dir.read("commit-msg").contains("'new.yaml'"));
+        // The chained hook is neither overwritten nor chained twice
+        assert_eq!(dir.read("commit-msg.pre-comgen"), EXISTING);
+        assert_eq!(std::fs::read_dir(&dir.0).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn install_refuses_to_overwrite_a_chained_hook() {
+        let dir = HooksDir::new("conflict");
+        let installer = dir.installer();
+        std::fs::create_dir_all(&dir.0).unwrap();
+        std::fs::write(installer.path(HookKind::CommitMsg), EXISTING).unwrap();
+        std::fs::write(dir.0.join("commit-msg.pre-comgen"), "#!/bin/sh\n").unwrap();
+
+        let result = installer.install(HookKind::CommitMsg, "comgen", "config.yaml");
+        assert!(matches!(result, Err(HookError::Conflict(_))));
+        assert_eq!(dir.read("commit-msg"), EXISTING);
+    }
+
+    #[test]
+    fn uninstall_leaves_other_hooks_alone() {
+        let dir = HooksDir::new("uninstall");
+        let installer = dir.installer();
+        assert!(!installer.uninstall(HookKind::CommitMsg).unwrap());
+
+        std::fs::create_dir_all(&dir.0).unwrap();
+        std::fs::write(installer.path(HookKind::CommitMsg), EXISTING).unwrap();
+        assert!(!installer.uninstall(HookKind::CommitMsg).unwrap());
+        assert_eq!(dir.read("commit-msg"), EXISTING);
+    }
+
+    #[test]
+    fn shell_quote_escapes_single_quotes() {
+        assert_eq!(
+            shell_quote("/opt/my tools/comgen"),
+            "'/opt/my tools/comgen'"
+        );
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+}
@@ -0,0 +1,92 @@
+/// Line below which git drops the message, as written by `git commit --verbose`
+const SCISSORS: &str = "------------------------ >8 ------------------------";
+
+/// Checks whether the `prepare-commit-msg` hook should write the message
+///
+/// Messages given with `-m` or `-F`, merges, squashes and amended or reused
+/// commits already have a message, only new commits are filled.
+///
+/// # Arguments
+/// * `source` - Second argument of the hook, `message`, `template`, `merge`,
+///   `squash` or `commit`, if any
+pub fn should_fill_message(source: Option<&str>) -> bool {
+    matches!(source, None | Some("") | Some("template"))
+}
+
+/// Returns the message git will record, without its comment lines
+///
+/// # Arguments
+/// * `content` - Content of the commit message file
+///
+/// # Returns
+/// The message up to the scissors line, comment lines removed and surrounding
+/// blank lines trimmed
+pub fn commit_message_body(content: &str) -> String {
+    content
+        .lines()
+        .take_while(|line| !line.contains(SCISSORS))
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Checks whether a message was written by git itself, such as for a merge or
+/// revert, or for a commit to be squashed by `rebase --autosquash`
+///
+/// # Arguments
+/// * `message` - The commit message
+pub fn is_generated_by_git(message: &str) -> bool {
+    ["Merge ", "Revert \"", "fixup! ", "squash! ", "amend! "]
+        .iter()
+        .any(|prefix| message.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_fill_message_only_for_new_commits() {
+        assert!(should_fill_message(None));
+        assert!(should_fill_message(Some("")));
+        assert!(should_fill_message(Some("template")));
+        for source in ["message", "merge", "squash", "commit"] {
+            assert!(!should_fill_message(Some(source)), "{}", source);
+        }
+    }
+
+    #[test]
+    fn commit_message_body_drops_comments() {
+        let content = "\n\nfeat: add login\n# Please enter the commit message\n\nBody line\n#\n";
+        assert_eq!(commit_message_body(content), "feat: add login\n\nBody line");
+    }
+
+    #[test]
+    fn commit_message_body_stops_at_the_scissors() {
+        let content = format!(
+            "fix: handle empty input\n\n# {}\n# Do not modify or remove the line above.\ndiff --git a/src/lib.rs b/src/lib.rs\n+added line\n",
+            SCISSORS
+        );
+        assert_eq!(commit_message_body(&content), "fix: handle empty input");
+    }
+
+    #[test]
+    fn commit_message_body_of_an_empty_message_is_empty() {
+        assert_eq!(
+            commit_message_body("\n# Please enter the commit message\n"),
+            ""
+        );
+    }
+
+    #[test]
+    fn is_generated_by_git_recognizes_git_messages() {
+        assert!(is_generated_by_git("Merge branch 'main' into feature"));
+        assert!(is_generated_by_git("Revert \"feat: add login\""));
+        assert!(is_generated_by_git("fixup! feat: add login"));
+        assert!(is_generated_by_git("squash! feat: add login"));
+        assert!(!is_generated_by_git("feat: merge user settings"));
+        assert!(!is_generated_by_git("Reverting the cache"));
+    }
+}
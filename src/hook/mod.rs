@@ -0,0 +1,6 @@
+mod error;
+mod installer;
+mod message;
+
+pub use installer::{HookInstaller, HookKind, InstallOutcome};
+pub use message::{commit_message_body, is_generated_by_git, should_fill_message};
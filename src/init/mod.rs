@@ -0,0 +1,7 @@
+mod template;
+mod writer;
+
+pub use template::template_from_history;
+pub use writer::{
+    render_config, validate_config, write_config, AuditChoice, InitAnswers, KeySource,
+};
@@ -0,0 +1,96 @@
+use crate::changelog::parse_subject;
+use crate::config::{CommitTemplate, LocalTemplate};
+use crate::git::LogEntry;
+
+/// Share of conventional subjects below which the history is not followed
+const MIN_CONVENTIONAL_RATIO: f64 = 0.5;
+
+/// Bounds of the maximum subject length
+const MIN_LENGTH: usize = 50;
+const MAX_LENGTH: usize = 100;
+
+/// Number of example messages kept
+const EXAMPLES: usize = 3;
+
+/// Builds a commit template following the conventions of the commit history
+///
+/// When most subjects follow `type(scope): description`, the commit types are
+/// those of the history, most used first, the maximum length covers 95% of
+/// the subjects and recent subjects serve as examples. Otherwise the default
+/// template is kept, so the project can start following it.
+///
+/// # Arguments
+/// * `entries` - Recent commits, newest first
+/// * `default` - Template used when the history follows no convention
+///
+/// # Returns
+/// The template, and whether it was derived from the history
+pub fn template_from_history(
+    entries: &[LogEntry],
+    default: &LocalTemplate,
+) -> (LocalTemplate, bool) {
+    let subjects: Vec<_> = entries
+        .iter()
+        .filter_map(|entry| parse_subject(&entry.subject).map(|parsed| (&entry.subject, parsed)))
+        .collect();
+    if subjects.is_empty()
+        || (subjects.len() as f64) < entries.len() as f64 * MIN_CONVENTIONAL_RATIO
+    {
+        return (default.clone(), false);
+    }
+
+    // Types by decreasing use, the first seen winning ties
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for (_, parsed) in &subjects {
+        match counts
+            .iter_mut()
+            .find(|(kind, _)| *kind == parsed.commit_type)
+        {
+            Some((_, count)) => *count += 1,
+            None => counts.push((parsed.commit_type, 1)),
+        }
+    }
+    counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+    let mut lengths: Vec<usize> = subjects
+        .iter()
+        .map(|(subject, _)| subject.chars().count())
+        .collect();
+    lengths.sort_unstable();
+    let covered = lengths[(lengths.len() - 1) * 95 / 100];
+    let max_length = covered
+        .div_ceil(10)
+        .saturating_mul(10)
+        .clamp(MIN_LENGTH, MAX_LENGTH);
+
+    // Recent subjects of different types
+    let mut examples: Vec<String> = Vec::new();
+    let mut example_types = Vec::new();
+    for (subject, parsed) in &subjects {
+        if example_types.len() == EXAMPLES {
+            break;
+        }
+        if !example_types.contains(&parsed.commit_type) && subject.chars().count() <= max_length {
+            example_types.push(parsed.commit_type);
+            examples.push(subject.to_string());
+        }
+    }
+
+    let scoped = subjects.iter().any(|(_, parsed)| parsed.scope.is_some());
+    let template = if scoped {
+        "<type>[optional scope]: <description>"
+    } else {
+        "<type>: <description>"
+    };
+
+    let derived = LocalTemplate {
+        commit_types: counts.iter().map(|(kind, _)| kind.to_string()).collect(),
+        output_format: CommitTemplate {
+            template: template.to_string(),
+            max_length,
+            examples,
+        },
+        audit_profiles: Vec::new(),
+    };
+    (derived, true)
+}
@@ -0,0 +1,322 @@
+use crate::config::Config;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// Example configuration the written one starts from, keeping its comments
+const EXAMPLE_CONFIG: &str = include_str!("../../examples/config.example.yaml");
+
+/// Where the API key of the provider is read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeySource {
+    /// The provider's environment variable, keeping the key out of the file
+    Environment,
+    /// The configuration file itself
+    File(String),
+}
+
+/// How changes are audited before being committed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditChoice {
+    /// Local rules and the model
+    Full,
+    /// Local rules only, without requests
+    RulesOnly,
+    /// No audit
+    Disabled,
+}
+
+/// Answers to the questions of `comgen init`
+#[derive(Debug, Clone)]
+pub struct InitAnswers {
+    /// AI provider, such as `openai`
+    pub provider: String,
+    /// Model of the provider
+    pub model: String,
+    /// Where the API key is read from, for providers requiring one
+    pub key: KeySource,
+    /// URL of the Ollama API
+    pub ollama_url: String,
+    /// Executable run by the `command` provider
+    pub program: String,
+    /// How changes are audited
+    pub audit: AuditChoice,
+}
+
+/// Renders the configuration file for the answers
+///
+/// The example configuration is used as a base, so the settings left to their
+/// defaults stay documented in the written file.
+///
+/// # Arguments
+/// * `answers` - Answers to the questions of `comgen init`
+///
+/// # Returns
+/// The content of the configuration file
+pub fn render_config(answers: &InitAnswers) -> String {
+    let mut values = vec![
+        (None, "provider", quote(&answers.provider)),
+        (None, "model", quote(&answers.model)),
+        (None, "ollama_url", quote(&answers.ollama_url)),
+        (Some("command"), "program", quote(&answers.program)),
+        (
+            Some("audit"),
+            "enabled",
+            (answers.audit != AuditChoice::Disabled).to_string(),
+        ),
+        (
+            Some("audit"),
+            "llm",
+            (answers.audit == AuditChoice::Full).to_string(),
+        ),
+    ];
+    if let KeySource::File(key) = &answers.key {
+        match answers.provider.as_str() {
+            "openai" => values.push((None, "openai_api_key", quote(key))),
+            "anthropic" => values.push((None, "anthropic_api_key", quote(key))),
+            _ => {}
+        }
+    }
+
+    values.into_iter().fold(
+        EXAMPLE_CONFIG.to_string(),
+        |content, (section, key, value)| set_value(&content, section, key, &value),
+    )
+}
+
+/// Parses and validates a rendered configuration
+///
+/// The credentials of the provider are only checked when the key is stored in
+/// the file, as the environment variable may be set later.
+///
+/// # Arguments
+/// * `content` - The rendered configuration file
+/// * `key` - Where the API key is read from
+///
+/// # Returns
+/// * `Ok(Config)` - The configuration, as comgen will load it
+/// * `Err(String)` - Describing the invalid setting
+pub fn validate_config(content: &str, key: &KeySource) -> Result<Config, String> {
+    let mut config: Config = serde_yaml::from_str(content).map_err(|e| e.to_string())?;
+    config.apply_environment();
+    if *key != KeySource::Environment {
+        for entry in config.provider_chain() {
+            config.validate_provider(&entry)?;
+        }
+    }
+    config.validate_settings()?;
+    Ok(config)
+}
+
+/// Writes the configuration file, creating its directory
+///
+/// A file holding an API key is only readable by its owner, from its creation
+/// on.
+///
+/// # Arguments
+/// * `path` - Path of the configuration file
+/// * `content` - The rendered configuration file
+/// * `key` - Where the API key is read from
+///
+/// # Returns
+/// * `Ok(())` - If the file was written
+/// * `Err(std::io::Error)` - If the directory or the file cannot be written
+pub fn write_config(path: &Path, content: &str, key: &KeySource) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = match key {
+        KeySource::File(_) => create_private(path)?,
+        KeySource::Environment => File::create(path)?,
+    };
+    file.write_all(content.as_bytes())
+}
+
+/// Replaces the value of a key, at the top level or in a top-level section
+///
+/// # Arguments
+/// * `content` - The configuration file
+/// * `section` - Section holding the key, `None` for a top-level key
+/// * `key` - The key whose value is replaced
+/// * `value` - The new value, as written in YAML
+fn set_value(content: &str, section: Option<&str>, key: &str, value: &str) -> String {
+    let (indent, mut in_section) = match section {
+        Some(_) => ("  ", false),
+        None => ("", true),
+    };
+    let prefix = format!("{}{}:", indent, key);
+    let mut replaced = false;
+
+    let lines: Vec<String> = content
+        .lines()
+        .map(|line| {
+            if let Some(section) = section {
+                // A top-level key starts or ends the section
+                if !line.starts_with([' ', '#']) && !line.is_empty() {
+                    in_section = line.trim_end() == format!("{}:", section);
+                }
+            }
+            if in_section && !replaced && line.starts_with(&prefix) {
+                replaced = true;
+                format!("{} {}", prefix, value)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    lines.join("\n") + "\n"
+}
+
+/// Quotes a value as a YAML string
+fn quote(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+/// Creates or truncates a file readable by its owner only
+///
+/// An existing file is restricted before anything is written to it.
+#[cfg(unix)]
+fn create_private(path: &Path) -> std::io::Result<File> {
+    use std::fs::{OpenOptions, Permissions};
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.set_permissions(Permissions::from_mode(0o600))?;
+    Ok(file)
+}
+
+/// Creates or truncates a file, keeping the permissions other platforms manage differently
+#[cfg(not(unix))]
+fn create_private(path: &Path) -> std::io::Result<File> {
+    File::create(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &str = "\
+provider: \"openai\"
+# model: \"commented\"
+model: \"gpt-4o\"
+cache:
+  enabled: true
+  path: \"~/.comgen/cache\"
+audit:
+  # Audit each commit
+  enabled: true
+  rules:
+    enabled: true
+";
+
+    fn answers(provider: &str, key: KeySource, audit: AuditChoice) -> InitAnswers {
+        InitAnswers {
+            provider: provider.to_string(),
+            model: "test-model".to_string(),
+            key,
+            ollama_url: "http://localhost:11434".to_string(),
+            program: String::new(),
+            audit,
+        }
+    }
+
+    #[test]
+    fn set_value_replaces_top_level_keys() {
+        let content = set_value(CONTENT, None, "model", "\"llama3\"");
+        assert!(content.contains("\nmodel: \"llama3\"\n"));
+        assert!(content.contains("# model: \"commented\""));
+        assert_eq!(content.lines().count(), CONTENT.lines().count());
+    }
+
+    #[test]
+    fn set_value_stays_in_its_section() {
+        let content = set_value(CONTENT, Some("audit"), "enabled", "false");
+        assert!(content.contains("cache:\n  enabled: true\n"));
+        assert!(content.contains("  # Audit each commit\n  enabled: false\n"));
+        assert!(content.contains("  rules:\n    enabled: true\n"));
+    }
+
+    #[test]
+    fn set_value_leaves_missing_keys_alone() {
+        assert_eq!(set_value(CONTENT, None, "stream", "false"), CONTENT);
+        assert_eq!(
+            set_value(CONTENT, Some("usage"), "enabled", "false"),
+            CONTENT
+        );
+    }
+
+    #[test]
+    fn quote_escapes_yaml_strings() {
+        assert_eq!(quote("gpt-4o"), "\"gpt-4o\"");
+        assert_eq!(quote("a \"b\" \\ c"), r#""a \"b\" \\ c""#);
+    }
+
+    #[test]
+    fn rendered_config_loads_with_the_answers() {
+        let rendered = render_config(&answers(
+            "ollama",
+            KeySource::Environment,
+            AuditChoice::RulesOnly,
+        ));
+        let config = validate_config(&rendered, &KeySource::Environment).unwrap();
+        assert_eq!(config.provider, "ollama");
+        assert_eq!(config.model, "test-model");
+        assert_eq!(config.ollama_url, "http://localhost:11434");
+        assert!(config.audit.enabled);
+        assert!(!config.audit.llm);
+        // The comments of the example are kept
+        assert!(rendered.contains("# Deterministic rules matched against"));
+    }
+
+    #[test]
+    fn rendered_config_stores_the_key_of_the_provider() {
+        let key = KeySource::File("sk-test".to_string());
+        let rendered = render_config(&answers("openai", key, AuditChoice::Disabled));
+        assert!(rendered.contains("\nopenai_api_key: \"sk-test\"\n"));
+        assert!(rendered.contains("\nanthropic_api_key: \"\"\n"));
+        assert!(rendered.contains("audit:\n  enabled: false\n"));
+    }
+
+    #[test]
+    fn validate_config_reports_invalid_settings() {
+        let rendered = render_config(&answers(
+            "command",
+            KeySource::File(String::new()),
+            AuditChoice::Full,
+        ));
+        let error = validate_config(&rendered, &KeySource::File(String::new())).unwrap_err();
+        assert!(error.contains("Command program is required"), "{}", error);
+
+        assert!(validate_config("provider: [", &KeySource::Environment).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_config_restricts_files_holding_a_key() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("comgen-init-{}", std::process::id()));
+        let path = dir.join("nested").join("config.yaml");
+        let key = KeySource::File("sk-test".to_string());
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        write_config(&path, CONTENT, &key).unwrap();
+        let created = mode(&path);
+
+        // A previous world-readable file is restricted too
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        write_config(&path, "provider: \"openai\"\n", &key).unwrap();
+        let replaced = mode(&path);
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(created, 0o600);
+        assert_eq!(replaced, 0o600);
+        assert_eq!(content, "provider: \"openai\"\n");
+    }
+}
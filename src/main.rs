@@ -1,65 +1,113 @@
 mod audit;
+mod cache;
+mod changelog;
 mod cli;
 mod config;
+mod doctor;
+mod generator;
 mod git;
+mod hook;
+mod init;
 mod providers;
+mod usage;
 mod utils;
 
-use audit::parser::parse_audit_response;
-use cli::{Cli, Display};
-use config::Config;
-use git::GitCommands;
-use providers::AIProvider;
+use audit::export::{AuditReport, ExportFormat};
+use audit::model::AuditResult;
+use audit::record;
+use audit::rules::RuleEngine;
+use audit::service::{audit_combined, audit_files};
+use audit::suppression::{suppressions_path, Suppressions, SUPPRESSIONS_FILE};
+use cache::ResponseCache;
+use chrono::Local;
+use cli::{
+    AuditArgs, ChangelogArgs, Cli, Command, ConfigCommand, Display, HookCommand, HookRunArgs,
+};
+use config::{
+    AuditFailurePolicy, AuditMode, AuditRecord, Config, ProviderEntry, ANTHROPIC_API_KEY_VAR,
+    LOCAL_TEMPLATE_FILE, OPENAI_API_KEY_VAR,
+};
+use generator::{Generator, Task};
+use git::{DiffTarget, FileDiff, GitCommands};
+use hook::{
+    commit_message_body, is_generated_by_git, should_fill_message, HookInstaller, HookKind,
+    InstallOutcome,
+};
+use init::{AuditChoice, InitAnswers, KeySource};
+use providers::ProviderError;
 use std::io::{self, Write};
-use tracing::info;
-use utils::{Logger, Spinner};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use usage::UsageTracker;
+use utils::{Logger, Spinner, StringUtils, TaskProgress};
 
-/// Process a single modified file by generating a commit message and optionally performing an audit
+/// Progress of a file prepared in the background, sent in the order it happens
+enum FileEvent {
+    /// Outcome of the audit, sent only when auditing is enabled
+    Audit(Result<AuditResult, Box<dyn std::error::Error + Send + Sync>>),
+    /// A piece of the commit message, as streamed by the provider
+    Token(String),
+    /// The complete commit message and the prompt used to generate it
+    Message {
+        prompt: String,
+        result: Result<String, Box<dyn std::error::Error + Send + Sync>>,
+    },
+    /// The file could not be prepared
+    Failed(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// Messages generated per file with `--force` before giving up on the template
+const MAX_FORCED_ATTEMPTS: usize = 3;
+
+/// State shared by every file reviewed during a run
+struct Session<'a> {
+    /// The parsed command line arguments
+    cli: &'a Cli,
+    /// The application configuration
+    config: &'a Config,
+    /// Generator used for regenerating messages
+    generator: &'a Generator,
+    /// Display utility for user interaction
+    display: &'a Display,
+    /// Audit results collected for `--audit-output`
+    report: &'a Mutex<AuditReport>,
+    /// Acknowledged findings, hidden from the audit results
+    suppressions: &'a Mutex<Suppressions>,
+    /// Local rules applied to the added lines of each diff
+    rules: &'a Arc<RuleEngine>,
+}
+
+/// Returns the name recorded as the auditor of the changes
 ///
 /// # Arguments
-/// * `file` - The path to the file being processed
-/// * `provider` - The AI provider used for generating responses
 /// * `config` - The application configuration
-/// * `cli` - The parsed command line arguments
-/// * `spinner` - Progress spinner for visual feedback
-/// * `display` - Display utility for user interaction
 ///
 /// # Returns
-/// * `Ok(())` - If the file was processed successfully
-/// * `Err(Box<dyn std::error::Error>)` - If an error occurred during processing
-fn process_file(
-    file: &str,
-    provider: &Box<dyn AIProvider>,
-    config: &Config,
-    cli: &Cli,
-    spinner: &mut Spinner,
-    display: &Display,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Check if file is deleted
-    let is_deleted = GitCommands::get_modified_files()?
-        .iter()
-        .find(|(f, status)| f == file && status == "deleted")
-        .is_some();
-
-    if is_deleted {
-        spinner.finish_with_message(&format!("✗ File {} has been deleted", file));
-        return Ok(());
+/// The audit model, or `rules` when only the local rules audit the changes
+fn auditor(config: &Config) -> &str {
+    if !config.audit.llm {
+        return "rules";
     }
+    config.audit.model.as_deref().unwrap_or(&config.model)
+}
 
-    // Get diff for specific file
-    let file_diff = match GitCommands::get_diff(Some(file)) {
-        Ok(diff) => diff,
-        Err(e) => {
-            spinner.finish_with_message(&format!("✗ Error getting diff for {}", file));
-            return Err(e.into());
-        }
-    };
-
-    // Generate specific prompt for this file
+/// Builds the commit message prompt for a file diff
+///
+/// # Arguments
+/// * `config` - The application configuration
+/// * `file_diff` - The diff of the file being processed
+///
+/// # Returns
+/// The prompt sent to the provider
+fn build_commit_prompt(config: &Config, file_diff: &str) -> String {
     let commit_types = config.templates.commit_types.join("\n- ");
     let output_format = &config.templates.output_format;
 
-    let file_prompt = config.base_prompt.replace("{{git_diff}}", &file_diff)
+    config.base_prompt.replace("{{git_diff}}", file_diff)
         + "\n\nAllowed commit types:\n- "
         + &commit_types
         + "\n\nOutput format requirements:"
@@ -69,123 +117,1265 @@ fn process_file(
         + &output_format.max_length.to_string()
         + " characters"
         + "\n\nExample commits:\n- "
-        + &output_format.examples.join("\n- ");
+        + &output_format.examples.join("\n- ")
+}
 
-    // Perform audit first if enabled
-    let mut skip_audit = false;
-    if config.audit.enabled {
-        spinner.start("Performing code audit...");
+/// Applies the CLI prefix to a generated commit message
+///
+/// # Arguments
+/// * `cli` - The parsed command line arguments
+/// * `message` - The generated commit message
+fn apply_prefix(cli: &Cli, message: String) -> String {
+    if cli.prefix.is_empty() {
+        message
+    } else {
+        format!("[{}] {}", cli.prefix, message)
+    }
+}
 
-        let audit_prompt = config.audit.prompt.replace("{{git_diff}}", &file_diff);
-        match provider.generate_response(&audit_prompt) {
-            Ok(audit_response) => {
-                let audit_result = parse_audit_response(&audit_response)?;
-                display.show_audit_results(file, &audit_result);
-            }
+/// Generates the audit and commit message of a file in the background
+///
+/// # Arguments
+/// * `file` - The path to the file being processed
+/// * `generator` - Generator used for audits and commit messages
+/// * `config` - The application configuration
+/// * `rules` - Local rules applied to the added lines
+/// * `task` - Progress line reporting the state of this file
+/// * `events` - Channel receiving the audit, message tokens and final message
+async fn prepare_file(
+    file: &str,
+    generator: &Generator,
+    config: &Config,
+    rules: &RuleEngine,
+    task: &TaskProgress,
+    events: UnboundedSender<FileEvent>,
+) {
+    // Get diff for specific file, git blocking a thread of its own instead of a runtime worker
+    let path = file.to_string();
+    let diff: Result<String, Box<dyn std::error::Error + Send + Sync>> =
+        tokio::task::spawn_blocking(move || GitCommands::get_diff(Some(&path)))
+            .await
+            .map_err(Into::into)
+            .and_then(|diff| diff.map_err(Into::into));
+    let file_diff = match diff {
+        Ok(diff) => diff,
+        Err(e) => {
+            task.finish_with_message(&format!("✗ Error getting diff for {}", file));
+            let _ = events.send(FileEvent::Failed(e));
+            return;
+        }
+    };
+
+    // Perform audit first if enabled, rules being applied by the combined audit in `both` mode
+    if config.audit.enabled && config.audit.mode.per_file() {
+        task.set_state("auditing...");
+        let diffs = [FileDiff::new(file, &file_diff)];
+        let rules = (config.audit.mode == AuditMode::PerFile).then_some(rules);
+        let audit = audit_files(generator, &config.audit, rules, &diffs).await;
+        let _ = events.send(FileEvent::Audit(audit));
+    }
+
+    // Generate commit message for this file, forwarding tokens as they arrive
+    task.set_state("generating commit message...");
+    let prompt = build_commit_prompt(config, &file_diff);
+    let on_token = |token: &str| {
+        let _ = events.send(FileEvent::Token(token.to_string()));
+    };
+    let result = generator
+        .generate_stream(Task::Commit, &prompt, &on_token)
+        .await
+        .map_err(Into::into);
+
+    task.set_state(if result.is_ok() { "ready" } else { "failed" });
+    let _ = events.send(FileEvent::Message { prompt, result });
+}
+
+/// Walks the user through the audit and commit message of a single file
+///
+/// The view is paused while the user interacts with this file, so the message
+/// streamed in the background is rendered live in the preview box.
+///
+/// # Arguments
+/// * `file` - The path to the file being processed
+/// * `events` - Channel receiving the progress of the background generation
+/// * `session` - State shared by every file of the run
+/// * `spinner` - Progress view, paused while prompting the user
+/// * `task` - Progress line reporting the state of this file
+/// * `combined` - Results of the audit of the whole change set, if any
+///
+/// # Returns
+/// * `Ok(())` - If the file was processed successfully
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If an error occurred during processing
+async fn process_file(
+    file: &str,
+    events: &mut UnboundedReceiver<FileEvent>,
+    session: &Session<'_>,
+    spinner: &Spinner,
+    task: &TaskProgress,
+    combined: Option<&AuditResult>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config = session.config;
+    let mut event = events.recv().await;
+
+    spinner.pause();
+    let result = review_file(file, &mut event, events, session, task).await;
+    spinner.resume();
+
+    let (current_message, audit) = result?;
+    task.set_state("committing...");
+
+    // The commit is covered by its own audit and the part of the combined one concerning it
+    let audit = match (combined.map(|combined| combined.for_file(file)), audit) {
+        (Some(mut combined), Some(audit)) => {
+            combined.merge(audit);
+            Some(combined)
+        }
+        (combined, audit) => combined.or(audit),
+    };
+    let trailers = match &audit {
+        Some(audit) if config.audit.record == AuditRecord::Trailers => {
+            record::trailers(audit, auditor(config))
+        }
+        _ => Vec::new(),
+    };
+
+    // Stage and commit with accepted message
+    GitCommands::stage_file(file)?;
+    GitCommands::commit(&current_message, &trailers)?;
+    if let Some(audit) = &audit {
+        if config.audit.record == AuditRecord::Notes {
+            let note = record::note(audit, auditor(config))?;
+            GitCommands::add_note(record::NOTES_REF, "HEAD", &note)?;
+        }
+    }
+
+    if session.cli.commit_args().auto_push {
+        task.set_state("pushing...");
+        match GitCommands::push() {
+            Ok(_) => task.finish_with_message(&format!("✓ Pushed {}", file)),
             Err(e) => {
-                spinner.finish_with_message("✗ Error during audit");
-                print!("Audit failed. Continue anyway? [y/N]: ");
-                io::stdout().flush()?;
+                task.finish_with_message(&format!("✗ Error pushing {}", file));
+                return Err(e.into());
+            }
+        }
+    } else {
+        task.finish_with_message(&format!("✓ Committed {}", file));
+    }
+    Ok(())
+}
+
+/// Shows the audit and the streamed commit message of a file until the user accepts one
+///
+/// # Arguments
+/// * `file` - The path to the file being processed
+/// * `event` - The first event received for this file
+/// * `events` - Channel receiving the remaining events
+/// * `session` - State shared by every file of the run
+/// * `task` - Progress line reporting the state of this file
+///
+/// # Returns
+/// * `Ok((String, Option<AuditResult>))` - The accepted commit message, and the
+///   reviewed findings if the file was audited
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If generation failed, the audit
+///   blocked the commit or the user aborted
+async fn review_file(
+    file: &str,
+    event: &mut Option<FileEvent>,
+    events: &mut UnboundedReceiver<FileEvent>,
+    session: &Session<'_>,
+    task: &TaskProgress,
+) -> Result<(String, Option<AuditResult>), Box<dyn std::error::Error + Send + Sync>> {
+    let Session {
+        cli,
+        config,
+        display,
+        ..
+    } = *session;
+
+    // The audit, when enabled, arrives before the commit message
+    let mut audit_result = None;
+    match event.take() {
+        Some(FileEvent::Audit(audit)) => {
+            audit_result = check_audit(file, audit, session, Some(task))?;
+            *event = events.recv().await;
+        }
+        other => *event = other,
+    }
+
+    // Render the message as it is streamed by the background task
+    let mut preview_open = false;
+    let (prompt, mut current_message) = loop {
+        match event.take() {
+            Some(FileEvent::Token(token)) => {
+                if !preview_open {
+                    display.begin_commit_preview(file, &cli.prefix);
+                    preview_open = true;
+                }
+                display.write_preview_token(&token);
+            }
+            Some(FileEvent::Message {
+                prompt,
+                result: Ok(message),
+            }) => {
+                if !preview_open {
+                    display.begin_commit_preview(file, &cli.prefix);
+                    display.write_preview_token(&message);
+                }
+                break (prompt, message);
+            }
+            Some(FileEvent::Message { result: Err(e), .. }) | Some(FileEvent::Failed(e)) => {
+                if preview_open {
+                    println!();
+                }
+                task.finish_with_message(&format!("✗ Error generating message for {}", file));
+                return Err(e);
+            }
+            Some(FileEvent::Audit(_)) => {}
+            None => return Err(format!("Generation for {} stopped unexpectedly", file).into()),
+        }
+        *event = events.recv().await;
+    };
+
+    if cli.force {
+        // Accept the first message following the template, without asking
+        let mut attempts = 1;
+        loop {
+            display.end_commit_preview();
+            let Err(reason) = config.templates.validate_message(&current_message) else {
+                break;
+            };
+            if attempts == MAX_FORCED_ATTEMPTS {
+                task.finish_with_message(&format!("✗ No valid message for {}", file));
+                return Err(format!(
+                    "No valid commit message for {} after {} attempts: {}",
+                    file, attempts, reason
+                )
+                .into());
+            }
+
+            warn!("rejected commit message for {}: {}", file, reason);
+            current_message = regenerate_message(file, &prompt, session, task).await?;
+            attempts += 1;
+        }
+    } else {
+        while !display.confirm_commit_message()? {
+            current_message = regenerate_message(file, &prompt, session, task).await?;
+        }
+    }
+
+    Ok((apply_prefix(cli, current_message), audit_result))
+}
 
-                let mut input = String::new();
-                io::stdin().read_line(&mut input)?;
+/// Shows an audit and decides whether the commits it covers may proceed
+///
+/// # Arguments
+/// * `audited` - What was audited, a file or the whole change set
+/// * `audit` - Outcome of the audit
+/// * `session` - State shared by every file of the run
+/// * `task` - Progress line of the audited file, if a single file was audited
+///
+/// # Returns
+/// * `Ok(Some(AuditResult))` - The reviewed findings, if the commits may proceed
+/// * `Ok(None)` - If the audit failed and the commits proceed without audit
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If the audit failed or
+///   blocked the commits
+fn check_audit(
+    audited: &str,
+    audit: Result<AuditResult, Box<dyn std::error::Error + Send + Sync>>,
+    session: &Session<'_>,
+    task: Option<&TaskProgress>,
+) -> Result<Option<AuditResult>, Box<dyn std::error::Error + Send + Sync>> {
+    let finish = |message: String| {
+        if let Some(task) = task {
+            task.finish_with_message(&message);
+        }
+    };
+    let Session { cli, config, .. } = *session;
+
+    match audit {
+        Ok(mut audit_result) => {
+            review_findings(&mut audit_result, session)?;
+            let blocked = config.audit.block_on_critical
+                && audit_result.has_blocking_issues(|category| config.audit.fail_on(category));
+            if let Ok(mut report) = session.report.lock() {
+                report.add(audit_result.clone());
+            }
+
+            // Findings reaching the gate of their profile block the commit unless forced
+            if blocked {
+                if !cli.force {
+                    finish(format!("✗ Commit blocked for {}", audited));
+                    return Err(format!(
+                        "Blocking audit findings in {}, use --force to commit anyway",
+                        audited
+                    )
+                    .into());
+                }
+                warn!("committing {} despite blocking audit findings", audited);
+            }
+            Ok(Some(audit_result))
+        }
+        Err(e) => {
+            println!("✗ Error during audit of {}: {}", audited, e);
+            let proceed = match config.audit.on_failure {
+                AuditFailurePolicy::Continue => true,
+                AuditFailurePolicy::Abort => false,
+                // Nobody can answer when running non-interactively
+                AuditFailurePolicy::Ask if cli.force => false,
+                AuditFailurePolicy::Ask => {
+                    print!("Audit failed. Continue anyway? [y/N]: ");
+                    io::stdout().flush()?;
 
-                if input.trim().to_lowercase() == "y" {
-                    skip_audit = true;
-                } else {
-                    return Err(e.into());
+                    let mut input = String::new();
+                    io::stdin().read_line(&mut input)?;
+                    input.trim().to_lowercase() == "y"
                 }
+            };
+
+            if !proceed {
+                finish(format!("✗ Audit failed for {}", audited));
+                return Err(e);
             }
+            Ok(None)
+        }
+    }
+}
+
+/// Hides the suppressed findings of an audit, shows the others and lets the user
+/// suppress some of them
+///
+/// Findings suppressed by the user are written to the suppression file and
+/// removed from the results, so they no longer block the commit.
+///
+/// # Arguments
+/// * `audit_result` - Results of the audit, updated in place
+/// * `session` - State shared by every file of the run
+///
+/// # Returns
+/// * `Ok(())` - If the findings were reviewed
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If prompting the user or
+///   writing the suppression file failed
+fn review_findings(
+    audit_result: &mut AuditResult,
+    session: &Session<'_>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let Session { cli, display, .. } = *session;
+    let today = Local::now().date_naive();
+
+    if let Ok(suppressions) = session.suppressions.lock() {
+        suppressions.apply(audit_result, today);
+    }
+    display.show_audit_results(audit_result, cli.audit_level);
+
+    // Nobody can answer when running non-interactively
+    if cli.force {
+        return Ok(());
+    }
+    let selected = display.select_suppressions(audit_result, cli.audit_level)?;
+    if selected.is_empty() {
+        return Ok(());
+    }
+    let Some((reason, expires)) = display.ask_suppression_details()? else {
+        return Ok(());
+    };
+
+    if let Ok(mut suppressions) = session.suppressions.lock() {
+        for &index in &selected {
+            suppressions.add(&audit_result.issues[index], &reason, expires);
         }
+        suppressions.save(&suppressions_path())?;
+        suppressions.apply(audit_result, today);
     }
+    println!(
+        "✓ Suppressed {} findings in {}",
+        selected.len(),
+        SUPPRESSIONS_FILE
+    );
+    Ok(())
+}
+
+/// Streams a new commit message into a fresh preview box, bypassing the cache
+///
+/// # Arguments
+/// * `file` - The path to the file being processed
+/// * `prompt` - The prompt used to generate the rejected message
+/// * `session` - State shared by every file of the run
+/// * `task` - Progress line reporting the state of this file
+///
+/// # Returns
+/// * `Ok(String)` - The new commit message, without prefix
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If generation failed
+async fn regenerate_message(
+    file: &str,
+    prompt: &str,
+    session: &Session<'_>,
+    task: &TaskProgress,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let display = session.display;
+    task.set_state("generating new commit message...");
+    display.begin_commit_preview(file, &session.cli.prefix);
 
-    if !skip_audit {
-        spinner.start("Generating commit message...");
+    let on_token = |token: &str| display.write_preview_token(token);
+    match session
+        .generator
+        .regenerate_stream(Task::Commit, prompt, &on_token)
+        .await
+    {
+        Ok(message) => Ok(message),
+        Err(e) => {
+            println!();
+            task.finish_with_message("✗ Error generating new message");
+            Err(e.into())
+        }
     }
+}
+
+/// Event stream and handle of a file being prepared in the background
+type Job = (UnboundedReceiver<FileEvent>, JoinHandle<()>);
 
-    // Generate commit message for this file
-    match provider.generate_response(&file_prompt) {
-        Ok(mut file_response) => {
-            if !cli.prefix.is_empty() {
-                file_response = format!("[{}] {}", cli.prefix, file_response);
+/// Audits and commits the modified files one by one
+///
+/// Audits and commit messages are generated concurrently on the tokio runtime,
+/// bounded by the configured concurrency, while the user approves files in order.
+///
+/// # Arguments
+/// * `session` - State shared by every file of the run
+/// * `generator` - Generator shared with the background tasks
+/// * `config` - Configuration shared with the background tasks
+/// * `concurrency` - Maximum number of files prepared at the same time
+///
+/// # Returns
+/// * `Ok(())` - If every file was committed
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If processing was aborted
+async fn commit_files(
+    session: &Session<'_>,
+    generator: &Arc<Generator>,
+    config: &Arc<Config>,
+    concurrency: usize,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut spinner = Spinner::new();
+
+    // Get modified files
+    spinner.start("Analyzing repository...");
+    let modified_files = GitCommands::get_modified_files()?;
+    spinner.finish();
+
+    session.display.display_files(&modified_files);
+
+    // Audit the whole change set first, so a blocking finding stops the run early
+    let mut combined = None;
+    if config.audit.enabled && config.audit.mode.combined() {
+        spinner.start("Auditing changes...");
+        // Read the diffs on the blocking pool, as the per-file jobs do
+        let files: Vec<String> = modified_files
+            .iter()
+            .filter(|(_, status)| status != "deleted")
+            .map(|(file, _)| file.clone())
+            .collect();
+        let diffs: Result<Vec<FileDiff>, Box<dyn std::error::Error + Send + Sync>> =
+            tokio::task::spawn_blocking(move || {
+                files
+                    .iter()
+                    .map(|file| {
+                        GitCommands::get_diff(Some(file)).map(|diff| FileDiff::new(file, &diff))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .await
+            .map_err(Into::into)
+            .and_then(|diffs| diffs.map_err(Into::into));
+        let diffs = diffs?;
+        let audit = audit_combined(generator, &config.audit, Some(session.rules), &diffs).await;
+        spinner.finish();
+        combined = check_audit("the changes", audit, session, None)?;
+    }
+
+    // Start generating audits and messages for every file in the background
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let mut jobs: Vec<(String, TaskProgress, Option<Job>)> = Vec::new();
+
+    for (file, status) in &modified_files {
+        let task = spinner.add_task(file);
+
+        if status == "deleted" {
+            task.finish_with_message(&format!("✗ File {} has been deleted", file));
+            jobs.push((file.clone(), task, None));
+            continue;
+        }
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let handle = tokio::spawn({
+            let file = file.clone();
+            let generator = Arc::clone(generator);
+            let config = Arc::clone(config);
+            let rules = Arc::clone(session.rules);
+            let semaphore = Arc::clone(&semaphore);
+            let task = task.clone();
+            async move {
+                if let Ok(_permit) = semaphore.acquire_owned().await {
+                    prepare_file(&file, &generator, &config, &rules, &task, sender).await;
+                }
             }
+        });
+        jobs.push((file.clone(), task, Some((receiver, handle))));
+    }
+
+    // Walk through the files in order while later files finish in the background
+    let total = jobs.len();
+    spinner.start("Processing files...");
+
+    let mut result = Ok(());
+    for (index, (file, task, job)) in jobs.iter_mut().enumerate() {
+        let Some((receiver, _)) = job else {
+            continue;
+        };
+
+        spinner.update_message(&format!("Processing files ({}/{})...", index + 1, total));
+
+        result = process_file(file, receiver, session, &spinner, task, combined.as_ref()).await;
+
+        if result.is_err() {
+            spinner.finish_with_message("✗ Processing aborted");
+            break;
+        }
+    }
+    spinner.finish();
 
+    // Cancel any generation still pending after an error
+    for (_, _, job) in jobs {
+        if let Some((_, handle)) = job {
+            handle.abort();
+        }
+    }
+    result
+}
+
+/// Audits the working tree, the staged changes, a commit or a range without committing
+///
+/// With `--baseline`, every finding not yet suppressed is recorded in the
+/// suppression file instead of being shown.
+///
+/// # Arguments
+/// * `args` - Arguments of the `audit` command
+/// * `session` - State shared by every file of the run
+///
+/// # Returns
+/// * `Ok(())` - If every file was audited and no finding meets the gate
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If an audit failed or
+///   findings at or above the `--fail-on` severity remain
+async fn run_audit(
+    args: &AuditArgs,
+    session: &Session<'_>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let Session {
+        config,
+        generator,
+        display,
+        rules,
+        ..
+    } = *session;
+    let mut spinner = Spinner::new();
+    let target = args.target();
+    // `--fail-on` replaces the gates of the profiles
+    let threshold = |category: Option<&str>| {
+        args.fail_on.or(config
+            .audit
+            .block_on_critical
+            .then(|| config.audit.fail_on(category)))
+    };
+
+    spinner.start("Analyzing repository...");
+    let changed_files = GitCommands::get_changed_files(&target)?;
+    spinner.finish();
+
+    let listed: Vec<_> = changed_files
+        .iter()
+        .map(|file| (file.clone(), "modified".to_string()))
+        .collect();
+    display.display_files(&listed);
+
+    let mut diffs = Vec::new();
+    for file in &changed_files {
+        diffs.push(FileDiff::new(
+            file,
+            &GitCommands::get_target_diff(&target, file)?,
+        ));
+    }
+
+    // Audit the change set as a whole and file by file, as configured
+    let mode = config.audit.mode;
+    let mut audits = Vec::new();
+    if mode.combined() && !diffs.is_empty() {
+        spinner.start("Auditing changes...");
+        let audit = audit_combined(generator, &config.audit, Some(rules), &diffs).await;
+        spinner.finish();
+        audits.push(audit.map_err(|e| format!("Audit of the changes failed: {}", e))?);
+    }
+    if mode.per_file() {
+        let rules = (mode == AuditMode::PerFile).then_some(rules.as_ref());
+        for diff in &diffs {
+            spinner.start(&format!("Auditing {}...", diff.file));
+            let audit =
+                audit_files(generator, &config.audit, rules, std::slice::from_ref(diff)).await;
             spinner.finish();
+            audits.push(audit.map_err(|e| format!("Audit of {} failed: {}", diff.file, e))?);
+        }
+    }
 
-            let mut accepted = false;
-            let mut current_message = file_response;
-
-            while !accepted {
-                match display.prompt_commit_message(file, &current_message) {
-                    Ok(true) => {
-                        accepted = true;
-                    }
-                    Ok(false) => {
-                        spinner.start("Generating new commit message...");
-
-                        match provider.generate_response(&file_prompt) {
-                            Ok(new_response) => {
-                                current_message = if !cli.prefix.is_empty() {
-                                    format!("[{}] {}", cli.prefix, new_response)
-                                } else {
-                                    new_response
-                                };
-                                spinner.finish();
-                            }
-                            Err(e) => {
-                                spinner.finish_with_message("✗ Error generating new message");
-                                return Err(e.into());
-                            }
-                        }
-                    }
-                    Err(e) => return Err(e.into()),
+    let today = Local::now().date_naive();
+    let reason = format!("Baseline recorded on {}", today);
+    let mut recorded = 0;
+    let mut failing = 0;
+    let mut reviewed: Option<AuditResult> = None;
+    for mut audit_result in audits {
+        if args.baseline {
+            if let Ok(mut suppressions) = session.suppressions.lock() {
+                suppressions.apply(&mut audit_result, today);
+                for issue in &audit_result.issues {
+                    suppressions.add(issue, &reason, None);
                 }
             }
+            recorded += audit_result.issues.len();
+        } else {
+            review_findings(&mut audit_result, session)?;
+            failing += audit_result
+                .issues
+                .iter()
+                .filter(|issue| {
+                    threshold(issue.category.as_deref())
+                        .is_some_and(|threshold| issue.severity >= threshold)
+                })
+                .count();
+            match &mut reviewed {
+                Some(reviewed) => reviewed.merge(audit_result.clone()),
+                None => reviewed = Some(audit_result.clone()),
+            }
+        }
+        if let Ok(mut report) = session.report.lock() {
+            report.add(audit_result);
+        }
+    }
+
+    // An audited commit can be annotated after the fact
+    if let (DiffTarget::Commit(rev), Some(reviewed)) = (&target, &reviewed) {
+        if config.audit.record == AuditRecord::Notes {
+            let note = record::note(reviewed, auditor(config))?;
+            GitCommands::add_note(record::NOTES_REF, rev, &note)?;
+            println!(
+                "✓ Recorded the audit of {} in refs/notes/{}",
+                rev,
+                record::NOTES_REF
+            );
+        }
+    }
+
+    if args.baseline {
+        if let Ok(suppressions) = session.suppressions.lock() {
+            suppressions.save(&suppressions_path())?;
+        }
+        println!("✓ Recorded {} findings in {}", recorded, SUPPRESSIONS_FILE);
+        return Ok(());
+    }
+
+    match args.fail_on {
+        _ if failing == 0 => Ok(()),
+        Some(threshold) => {
+            Err(format!("{} audit findings at or above {}", failing, threshold).into())
+        }
+        None => Err(format!(
+            "{} audit findings at or above the gate of their profile",
+            failing
+        )
+        .into()),
+    }
+}
+
+/// Installs the comgen hooks in the hooks directory of the repository
+///
+/// # Arguments
+/// * `config_path` - Configuration file the hooks load
+/// * `commit_msg` - Whether the commit-msg hook is installed too
+///
+/// # Returns
+/// * `Ok(())` - If the hooks were installed
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If the repository or
+///   an existing hook prevents the installation
+fn install_hooks(
+    config_path: &str,
+    commit_msg: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let installer = HookInstaller::new(GitCommands::hooks_dir()?);
+    let program = std::env::current_exe()?;
+    // Hooks run from the top level of the working tree, not from here
+    let config_path = if config_path.starts_with('~') || Path::new(config_path).is_absolute() {
+        config_path.to_string()
+    } else {
+        std::env::current_dir()?
+            .join(config_path)
+            .to_string_lossy()
+            .into_owned()
+    };
+
+    let mut kinds = vec![HookKind::PrepareCommitMsg];
+    if commit_msg {
+        kinds.push(HookKind::CommitMsg);
+    }
+    for kind in kinds {
+        let path = installer.path(kind);
+        match installer.install(kind, &program.to_string_lossy(), &config_path)? {
+            InstallOutcome::Installed => println!("✓ Installed {}", path.display()),
+            InstallOutcome::Updated => println!("✓ Updated {}", path.display()),
+            InstallOutcome::Chained(chained) => println!(
+                "✓ Installed {}, the existing hook moved to {} runs first",
+                path.display(),
+                chained.display()
+            ),
+        }
+    }
+    Ok(())
+}
 
-            spinner.start(&format!("Committing {}", file));
+/// Removes the comgen hooks, restoring the hooks they chained
+///
+/// # Returns
+/// * `Ok(())` - If the hooks were removed or none was installed
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If a hook cannot be removed
+fn uninstall_hooks() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let installer = HookInstaller::new(GitCommands::hooks_dir()?);
+    let mut removed = 0;
+    for kind in [HookKind::PrepareCommitMsg, HookKind::CommitMsg] {
+        if installer.uninstall(kind)? {
+            println!("✓ Removed {}", installer.path(kind).display());
+            removed += 1;
+        }
+    }
+    if removed == 0 {
+        println!("No comgen hooks installed");
+    }
+    Ok(())
+}
+
+/// Runs a hook invoked by git
+///
+/// `prepare-commit-msg` writes a message generated from the staged diff
+/// above the content git prepared, unless the commit already has a message.
+/// Any failure, including a missing configuration or an unreachable provider,
+/// is reported without blocking the commit.
+/// `commit-msg` rejects messages that do not follow the template, except
+/// those written by git itself, and only reads the configuration.
+///
+/// # Arguments
+/// * `cli` - The command line arguments
+/// * `args` - The hook and the arguments git passed to it
+/// * `config_path` - Path of the configuration file
+///
+/// # Returns
+/// * `Ok(())` - If the commit may proceed
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If the message is rejected,
+///   or the message file or the configuration cannot be read by `commit-msg`
+async fn run_hook(
+    cli: &Cli,
+    args: &HookRunArgs,
+    config_path: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let Some(message_file) = args.args.first() else {
+        return Err(format!("{} expects the commit message file", args.hook.file_name()).into());
+    };
 
-            // Stage and commit with accepted message
-            GitCommands::stage_file(file)?;
-            GitCommands::commit(&current_message)?;
+    match args.hook {
+        HookKind::PrepareCommitMsg => {
+            let source = args.args.get(1).map(String::as_str);
+            if let Err(e) = prepare_commit_message(cli, config_path, message_file, source).await {
+                warn!("failed to generate commit message in hook: {}", e);
+                eprintln!("comgen: no commit message generated: {}", e);
+            }
+            Ok(())
+        }
+        HookKind::CommitMsg => {
+            let content = std::fs::read_to_string(message_file)?;
+            let message = commit_message_body(&content);
+            if is_generated_by_git(&message) {
+                return Ok(());
+            }
+            // Checking the template needs no provider, nor its credentials
+            let mut config = config::read_config(config_path).map_err(|e| e.to_string())?;
+            config.load_local_template()?;
+            config
+                .templates
+                .validate_message(&message)
+                .map_err(|reason| format!("Commit message rejected by comgen: {}", reason).into())
+        }
+    }
+}
 
-            if cli.auto_push {
-                match GitCommands::push() {
-                    Ok(_) => spinner.finish_with_message(&format!("✓ Pushed {}", file)),
-                    Err(e) => {
-                        spinner.finish_with_message(&format!("✗ Error pushing {}", file));
-                        return Err(e.into());
-                    }
+/// Writes a message generated from the staged diff into the commit message file
+///
+/// # Arguments
+/// * `cli` - The command line arguments
+/// * `config_path` - Path of the configuration file
+/// * `message_file` - The commit message file prepared by git
+/// * `source` - Source of the message, as passed by git to the hook
+///
+/// # Returns
+/// * `Ok(())` - If the message was written, or none was needed
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If the configuration, the
+///   providers or generation failed, or the message file cannot be written
+async fn prepare_commit_message(
+    cli: &Cli,
+    config_path: &str,
+    message_file: &str,
+    source: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if !should_fill_message(source) {
+        return Ok(());
+    }
+    let content = std::fs::read_to_string(message_file)?;
+    let diff = GitCommands::get_staged_diff()?;
+    if diff.trim().is_empty() {
+        return Ok(());
+    }
+
+    let mut config = config::load_config(config_path).map_err(|e| e.to_string())?;
+    if let Err(e) = config.load_local_template() {
+        info!("No local template found or error loading it: {}", e);
+    }
+    let generator = create_generator(cli, &config)?;
+    let message = generate_message(&diff, cli, &config, &generator).await;
+
+    if let Err(e) = usage::append_ledger(&config.usage.ledger, &generator.usage().summary()) {
+        warn!("failed to update usage ledger: {}", e);
+    }
+    std::fs::write(message_file, format!("{}\n{}", message?, content))?;
+    Ok(())
+}
+
+/// Generates a single commit message for a diff, without streaming or review
+///
+/// # Arguments
+/// * `diff` - The diff the message describes
+/// * `cli` - The command line arguments
+/// * `config` - The application configuration
+/// * `generator` - Generator used for the message
+///
+/// # Returns
+/// * `Ok(String)` - The message, prefixed as requested on the command line
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If generation failed
+async fn generate_message(
+    diff: &str,
+    cli: &Cli,
+    config: &Config,
+    generator: &Generator,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let prompt = build_commit_prompt(config, diff);
+    let message = generator.generate(Task::Commit, &prompt, None).await?;
+    Ok(apply_prefix(cli, message.trim().to_string()))
+}
+
+/// Prints a commit message generated for the staged changes
+///
+/// Only the message is written to stdout, so editors and scripts can use it.
+///
+/// # Arguments
+/// * `session` - State shared by the run
+///
+/// # Returns
+/// * `Ok(())` - If the message was printed
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If nothing is staged or
+///   generation failed
+async fn run_message(
+    session: &Session<'_>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let diff = GitCommands::get_staged_diff()?;
+    if diff.trim().is_empty() {
+        return Err("No staged changes, stage them with git add first".into());
+    }
+    let Session {
+        cli,
+        config,
+        generator,
+        ..
+    } = *session;
+    println!("{}", generate_message(&diff, cli, config, generator).await?);
+    Ok(())
+}
+
+/// Editor used when neither `VISUAL` nor `EDITOR` is set
+#[cfg(windows)]
+const DEFAULT_EDITOR: &str = "notepad";
+#[cfg(not(windows))]
+const DEFAULT_EDITOR: &str = "vi";
+
+/// Prints, shows or edits the configuration file
+///
+/// # Arguments
+/// * `command` - Action on the configuration file
+/// * `config_path` - Path of the configuration file, as given on the command line
+///
+/// # Returns
+/// * `Ok(())` - If the action succeeded
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If the file cannot be read
+///   or the editor failed
+fn run_config_command(
+    command: &ConfigCommand,
+    config_path: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let path = config::expand_home(Path::new(config_path))?;
+    match command {
+        ConfigCommand::Path => println!("{}", path.display()),
+        ConfigCommand::Show => {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+            print!("{}", config::mask_secrets(&content));
+        }
+        ConfigCommand::Edit => {
+            let editor = std::env::var("VISUAL")
+                .or_else(|_| std::env::var("EDITOR"))
+                .unwrap_or_else(|_| DEFAULT_EDITOR.to_string());
+            // The editor may carry arguments, such as `code --wait`, and is run without
+            // a shell so it works the same on every platform
+            let mut words = StringUtils::split_args(&editor);
+            if words.is_empty() {
+                return Err("The editor set in VISUAL or EDITOR is empty".into());
+            }
+            let program = words.remove(0);
+            let status = std::process::Command::new(&program)
+                .args(&words)
+                .arg(&path)
+                .status()
+                .map_err(|e| format!("Cannot run editor {}: {}", program, e))?;
+            if !status.success() {
+                return Err(format!("Editor {} exited with {}", editor, status).into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks the configuration and the repository, printing a report with fixes
+///
+/// # Arguments
+/// * `config_path` - Path of the configuration file, as given on the command line
+///
+/// # Returns
+/// * `Ok(())` - If no check failed
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - Counting the failed checks
+async fn run_doctor(config_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut spinner = Spinner::new();
+    spinner.start("Running checks...");
+    let report = doctor::run_checks(config_path).await;
+    spinner.finish();
+    Display::new().show_doctor_report(&report);
+    match report.failures() {
+        0 => Ok(()),
+        failures => Err(format!("{} checks failed", failures).into()),
+    }
+}
+
+/// Providers offered by `comgen init`, with their default model
+const INIT_PROVIDERS: &[(&str, &str)] = &[
+    ("openai", "gpt-4o"),
+    ("anthropic", "claude-3-5-sonnet-latest"),
+    ("ollama", "llama3"),
+    ("command", "default"),
+];
+
+/// Kinds of models listed by OpenAI that cannot write commit messages
+const NON_CHAT_MODELS: &[&str] = &[
+    "embedding",
+    "whisper",
+    "tts",
+    "dall-e",
+    "moderation",
+    "transcribe",
+    "image",
+];
+
+/// Commits read to derive the template of a repository
+const TEMPLATE_HISTORY: usize = 200;
+
+/// Asks how to set up comgen and writes the configuration file
+///
+/// Optionally writes a `comgen.template` following the conventions of the
+/// commit history at the top level of the current repository.
+///
+/// # Arguments
+/// * `config_path` - Path of the configuration file, as given on the command line
+///
+/// # Returns
+/// * `Ok(())` - If the configuration was written, or left unchanged on request
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If an answer is missing,
+///   the configuration is invalid or cannot be written
+async fn run_init(config_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let display = Display::new();
+    let path = config::expand_home(Path::new(config_path))?;
+    if path.exists()
+        && !display.confirm(&format!("{} exists, overwrite it", path.display()), false)?
+    {
+        println!("Configuration left unchanged");
+        return Ok(());
+    }
+
+    let names: Vec<&str> = INIT_PROVIDERS.iter().map(|(name, _)| *name).collect();
+    let (provider, default_model) = INIT_PROVIDERS[display.choose("Provider", &names, 0)?];
+    let mut answers = InitAnswers {
+        provider: provider.to_string(),
+        model: default_model.to_string(),
+        key: KeySource::Environment,
+        ollama_url: Config::default().ollama_url,
+        program: String::new(),
+        audit: AuditChoice::Full,
+    };
+
+    let mut api_key = None;
+    match provider {
+        "openai" | "anthropic" => {
+            let var = if provider == "openai" {
+                OPENAI_API_KEY_VAR
+            } else {
+                ANTHROPIC_API_KEY_VAR
+            };
+            let from_environment = std::env::var(var).ok().filter(|key| !key.is_empty());
+            let environment = match from_environment {
+                Some(_) => format!("Environment variable {} (set)", var),
+                None => format!("Environment variable {} (not set yet)", var),
+            };
+            let sources = [environment.as_str(), "Configuration file"];
+            if display.choose("API key source", &sources, 0)? == 0 {
+                if from_environment.is_none() {
+                    println!("Set {} before running comgen", var);
                 }
+                api_key = from_environment;
             } else {
-                spinner.finish_with_message(&format!("✓ Committed {}", file));
+                let key = display.ask("API key", "")?;
+                if key.is_empty() {
+                    return Err("An API key is required".into());
+                }
+                api_key = Some(key.clone());
+                answers.key = KeySource::File(key);
             }
-            Ok(())
         }
-        Err(e) => {
-            spinner.finish_with_message(&format!("✗ Error generating message for {}", file));
-            Err(e.into())
+        "ollama" => answers.ollama_url = display.ask("Ollama URL", &answers.ollama_url)?,
+        _ => {
+            answers.program = display.ask("Command to run", "")?;
+            if answers.program.is_empty() {
+                return Err("A command is required by the command provider".into());
+            }
         }
     }
+
+    answers.model = select_model(&answers, api_key, &display).await?;
+
+    let audits = [
+        "Local rules and the model",
+        "Local rules only, without requests",
+        "Disabled",
+    ];
+    answers.audit = match display.choose("Audit changes before committing", &audits, 0)? {
+        0 => AuditChoice::Full,
+        1 => AuditChoice::RulesOnly,
+        _ => AuditChoice::Disabled,
+    };
+
+    let content = init::render_config(&answers);
+    let config = init::validate_config(&content, &answers.key)
+        .map_err(|e| format!("Invalid configuration: {}", e))?;
+    init::write_config(&path, &content, &answers.key)?;
+    println!("✓ Wrote {}", path.display());
+
+    if let Ok(toplevel) = GitCommands::toplevel() {
+        let template_path = toplevel.join(LOCAL_TEMPLATE_FILE);
+        let question = if template_path.exists() {
+            format!(
+                "Replace {} from the commit history",
+                template_path.display()
+            )
+        } else {
+            "Generate comgen.template from the commit history of this repository".to_string()
+        };
+        if display.confirm(&question, !template_path.exists())? {
+            // A repository without commits has no history to follow
+            let entries = GitCommands::get_log("HEAD", Some(TEMPLATE_HISTORY)).unwrap_or_default();
+            let (template, derived) = init::template_from_history(&entries, &config.templates);
+            std::fs::write(&template_path, serde_yaml::to_string(&template)?)?;
+            if derived {
+                println!(
+                    "✓ Wrote {} from {} commits",
+                    template_path.display(),
+                    entries.len()
+                );
+            } else {
+                println!(
+                    "✓ Wrote {} with the default commit types, the history following no convention",
+                    template_path.display()
+                );
+            }
+        }
+    }
+
+    println!("Run `comgen doctor` to check the setup");
+    Ok(())
+}
+
+/// Asks for the model, listing the models of the provider when it can
+///
+/// # Arguments
+/// * `answers` - Answers given so far, with the default model
+/// * `api_key` - API key to list the models with, if known
+/// * `display` - Display used to ask
+///
+/// # Returns
+/// * `Ok(String)` - The model
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If there's an IO error
+async fn select_model(
+    answers: &InitAnswers,
+    api_key: Option<String>,
+    display: &Display,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let entry = ProviderEntry {
+        provider: answers.provider.clone(),
+        model: answers.model.clone(),
+        api_key,
+        url: Some(answers.ollama_url.clone()),
+    };
+    let mut config = Config::default();
+    config.request.max_retries = 0;
+    config.command.program = answers.program.clone();
+
+    let needs_key = matches!(answers.provider.as_str(), "openai" | "anthropic");
+    let models = if needs_key && entry.api_key.is_none() {
+        None
+    } else {
+        let mut spinner = Spinner::new();
+        spinner.start("Listing models...");
+        let models = providers::build_provider(&entry, &config).models().await;
+        spinner.finish();
+        match models {
+            Ok(models) => models,
+            Err(e) => {
+                println!("Cannot list the models: {}", e);
+                None
+            }
+        }
+    };
+
+    let mut models = models.unwrap_or_default();
+    models.retain(|model| !NON_CHAT_MODELS.iter().any(|kind| model.contains(kind)));
+    models.sort();
+
+    let model = if models.is_empty() {
+        display.ask("Model", &answers.model)?
+    } else {
+        let default = if models.contains(&answers.model) {
+            &answers.model
+        } else {
+            &models[0]
+        };
+        display.select_model(&models, default)?
+    };
+    Ok(model)
+}
+
+/// Prints a Markdown changelog of the commits in a range
+///
+/// # Arguments
+/// * `args` - Arguments of the `changelog` command
+/// * `config` - The application configuration, ordering sections by commit type
+///
+/// # Returns
+/// * `Ok(())` - If the changelog was printed
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If the range is invalid
+fn run_changelog(
+    args: &ChangelogArgs,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let range = match &args.range {
+        Some(range) => range.clone(),
+        None => match GitCommands::latest_tag()? {
+            Some(tag) => format!("{}..HEAD", tag),
+            None => "HEAD".to_string(),
+        },
+    };
+    let entries = GitCommands::get_log(&range, None)?;
+    let title = format!("Changes in {}", range);
+    print!(
+        "{}",
+        changelog::build_changelog(&title, &entries, &config.templates.commit_types)
+    );
+    Ok(())
+}
+
+/// Creates the providers of the configuration, wrapped with the response cache
+///
+/// # Arguments
+/// * `cli` - The command line arguments
+/// * `config` - The application configuration
+///
+/// # Returns
+/// * `Ok(Generator)` - Generator for commit messages and audits
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If the cache cannot be opened
+fn create_generator(
+    cli: &Cli,
+    config: &Config,
+) -> Result<Generator, Box<dyn std::error::Error + Send + Sync>> {
+    let provider = providers::create_provider(config, None);
+    let audit_provider = match &config.audit.model {
+        Some(model) => providers::create_provider(config, Some(model)),
+        None => Arc::clone(&provider),
+    };
+    let cache = ResponseCache::new(&config.cache, config.cache.enabled && !cli.no_cache)?;
+    let usage = UsageTracker::new(
+        config.usage.pricing.clone(),
+        cli.budget.or(config.usage.budget),
+    );
+    Ok(Generator::new(
+        provider,
+        audit_provider,
+        &config.generation,
+        cache,
+        usage,
+    ))
 }
 
 /// Main entry point for the application
 ///
-/// Sets up logging, loads configuration, and processes modified files in the git repository
+/// Sets up logging, loads configuration, and commits the modified files of the git
+/// repository, or runs the command given on the command line.
 ///
 /// # Returns
 /// * `Ok(())` - If all operations completed successfully
-/// * `Err(Box<dyn std::error::Error>)` - If an error occurred during execution
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
-    Logger::setup()?;
-
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If an error occurred during execution
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // Parse CLI arguments
     let cli = Cli::parse_args();
     let config_path = cli.get_config_path()?;
+
+    // Initialize logging, the doctor reporting a log directory that cannot be written
+    // and hooks never blocking git on it
+    let logging = Logger::setup();
+    if !matches!(cli.command, Some(Command::Doctor) | Some(Command::Hook(_))) {
+        logging?;
+    }
+
+    // These commands need no configuration, or must work with a broken one
+    match &cli.command {
+        Some(Command::Hook(args)) => match &args.command {
+            HookCommand::Install(install) => {
+                return install_hooks(&config_path, install.commit_msg)
+            }
+            HookCommand::Uninstall => return uninstall_hooks(),
+            HookCommand::Run(run) => return run_hook(&cli, run, &config_path).await,
+        },
+        Some(Command::Config(args)) => return run_config_command(&args.command, &config_path),
+        Some(Command::Doctor) => return run_doctor(&config_path).await,
+        Some(Command::Init) => return run_init(&config_path).await,
+        _ => {}
+    }
     info!("loading config from {}", config_path);
 
     // Load configuration
-    let mut config = config::load_config(&config_path)?;
+    // The message tells how to create a missing file
+    let mut config = config::load_config(&config_path).map_err(|e| e.to_string())?;
 
     // Try to load local template if it exists
     if let Err(e) = config.load_local_template() {
@@ -194,37 +1384,96 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         info!("Local template loaded successfully");
     }
 
-    info!("using provider: {}", config.provider);
-    info!("using model: {}", config.model);
+    // Command line flags take precedence over the configuration
+    if let Some(enabled) = cli.commit_args().audit_override() {
+        config.audit.enabled = enabled;
+    }
+    if let Some(record) = cli.audit_record {
+        config.audit.record = record;
+    }
+    if !cli.audit_profiles.is_empty() {
+        config.audit.active_profiles = cli.audit_profiles.clone();
+    }
+    let audits = config.audit.enabled || matches!(cli.command, Some(Command::Audit(_)));
+    if audits && config.audit.llm {
+        config.audit.validate_profiles()?;
+    }
+    let concurrency = cli.commit_args().jobs.unwrap_or(config.concurrency).max(1);
 
-    // Create provider
-    let provider = providers::create_provider(&config.provider, &config);
+    for entry in config.provider_chain() {
+        info!(
+            "using provider: {} with model: {}",
+            entry.provider, entry.model
+        );
+    }
+    if let Some(model) = &config.audit.model {
+        info!("using audit model: {}", model);
+    }
+    info!("using concurrency: {}", concurrency);
 
-    // Initialize display utilities
-    let mut spinner = Spinner::new();
-    let display = Display::new();
+    let generator = Arc::new(create_generator(&cli, &config)?);
+    let config = Arc::new(config);
 
-    // Get modified files
-    spinner.start("Analyzing repository...");
-    let modified_files = GitCommands::get_modified_files()?;
-    spinner.finish();
+    let display = Display::new();
+    let report = Mutex::new(AuditReport::new());
+    let suppressions = Mutex::new(Suppressions::load(&suppressions_path())?);
+    let rules = Arc::new(RuleEngine::new(&config.audit.rules)?);
+    let session = Session {
+        cli: &cli,
+        config: &config,
+        generator: &generator,
+        display: &display,
+        report: &report,
+        suppressions: &suppressions,
+        rules: &rules,
+    };
 
-    display.display_files(&modified_files);
+    let mut result = match &cli.command {
+        None | Some(Command::Commit(_)) => {
+            commit_files(&session, &generator, &config, concurrency).await
+        }
+        Some(Command::Message) => run_message(&session).await,
+        Some(Command::Audit(args)) => run_audit(args, &session).await,
+        Some(Command::Changelog(args)) => run_changelog(args, &config),
+        Some(Command::Hook(_))
+        | Some(Command::Config(_))
+        | Some(Command::Doctor)
+        | Some(Command::Init) => Ok(()),
+    };
 
-    let files_list: Vec<String> = modified_files
-        .iter()
-        .map(|(file, _)| file.clone())
-        .collect();
+    // Export the audit findings, even when processing was aborted
+    if let Some(path) = &cli.audit_output {
+        let format = cli
+            .audit_format
+            .unwrap_or_else(|| ExportFormat::from_path(path));
+        let report = report.into_inner().unwrap_or_default();
+        match report.write(path, format) {
+            Ok(()) => info!("audit report written to {}", path.display()),
+            Err(e) if result.is_ok() => result = Err(e.into()),
+            Err(e) => warn!("failed to write audit report: {}", e),
+        }
+    }
 
-    spinner.start("Processing files...");
+    // Report and record the tokens consumed by this run
+    let usage = generator.usage();
+    let summary = usage.summary();
+    // Other commands print output meant for other programs, or use no tokens
+    if matches!(
+        cli.command,
+        None | Some(Command::Commit(_)) | Some(Command::Audit(_))
+    ) {
+        display.show_usage_summary(&summary, usage.total_cost(), usage.budget());
+    }
+    if let Err(e) = usage::append_ledger(&config.usage.ledger, &summary) {
+        warn!("failed to update usage ledger: {}", e);
+    }
 
-    // Process each file
-    for file in &files_list {
-        if let Err(e) = process_file(file, &provider, &config, &cli, &mut spinner, &display) {
-            return Err(e);
+    // Provider errors rarely tell what to change, the doctor does
+    if let Err(e) = &result {
+        if e.downcast_ref::<ProviderError>().is_some() {
+            eprintln!("Run `comgen doctor` to check the configuration and the provider");
         }
     }
 
-    spinner.finish();
-    Ok(())
+    result
 }
@@ -1,11 +1,38 @@
-use super::AIProvider;
+use super::retry::RetryPolicy;
+use super::stream::{sse_data, LineBuffer};
+use super::{
+    build_client, request_identity, AIProvider, Completion, CompletionRequest, ProviderError,
+    TokenSink, Usage,
+};
+use crate::config::{GenerationParams, RequestConfig};
+use async_trait::async_trait;
+use std::time::Duration;
+
+/// Endpoint of the Anthropic Messages API
+const MESSAGES_URL: &str = "https://api.anthropic.com/v1/messages";
+
+/// Endpoint listing the models of the Anthropic API
+const MODELS_URL: &str = "https://api.anthropic.com/v1/models?limit=1000";
+
+/// Token limit sent when none is configured, since the API requires one
+const DEFAULT_MAX_TOKENS: u32 = 1000;
 
 /// Provider implementation for Anthropic's AI models
 pub struct AnthropicProvider {
     /// The model identifier to use for requests
     model: String,
+    /// Shared HTTP client reused across requests
+    client: reqwest::Client,
     /// API key for authentication with Anthropic
     api_key: String,
+    /// Retry strategy applied to failed requests
+    retry: RetryPolicy,
+    /// Maximum duration of a non-streamed request
+    timeout: Duration,
+    /// Generation parameters of this provider, before task overrides
+    params: GenerationParams,
+    /// Whether responses are streamed as server-sent events
+    stream: bool,
 }
 
 impl AnthropicProvider {
@@ -14,50 +41,257 @@ impl AnthropicProvider {
     /// # Arguments
     /// * `model` - The model identifier to use
     /// * `api_key` - API key for authentication
-    pub fn new(model: &str, api_key: &str) -> Self {
+    /// * `stream` - Whether to stream responses when supported by the caller
+    /// * `params` - Generation parameters of this provider
+    /// * `request` - Timeout and retry settings
+    pub fn new(
+        model: &str,
+        api_key: &str,
+        stream: bool,
+        params: GenerationParams,
+        request: &RequestConfig,
+    ) -> Self {
         Self {
             model: model.to_string(),
+            client: build_client(request),
             api_key: api_key.to_string(),
+            retry: RetryPolicy::new(request),
+            timeout: Duration::from_secs(request.timeout_secs),
+            params,
+            stream,
         }
     }
-}
 
-impl AIProvider for AnthropicProvider {
-    /// Generates a response from the Anthropic API for the given prompt
+    /// Sends a Messages API request
     ///
     /// # Arguments
-    /// * `prompt` - The input prompt to send to the model
-    ///
-    /// # Returns
-    /// * `Ok(String)` - The generated response text
-    /// * `Err(Box<dyn Error>)` - If the API request fails
-    fn generate_response(&self, prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
+    /// * `request` - The prompt and task parameters to send to the model
+    /// * `stream` - Whether to request a server-sent event stream
+    async fn send(
+        &self,
+        request: &CompletionRequest<'_>,
+        stream: bool,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let params = self.params.merge(request.params);
+
         // Create the request body
-        let request_body = serde_json::json!({
+        let mut request_body = serde_json::json!({
             "model": self.model,
-            "prompt": format!("\n\nHuman: {}\n\nAssistant:", prompt),
-            "max_tokens_to_sample": 1000,
-            "temperature": 0.7
+            "messages": [
+                {
+                    "role": "user",
+                    "content": request.prompt
+                }
+            ],
+            "max_tokens": params.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            "stream": stream
         });
+        if let Some(temperature) = params.temperature {
+            request_body["temperature"] = serde_json::json!(temperature);
+        }
+        if let Some(top_p) = params.top_p {
+            request_body["top_p"] = serde_json::json!(top_p);
+        }
+        if !params.stop.is_empty() {
+            request_body["stop_sequences"] = serde_json::json!(params.stop);
+        }
+        if let Some(schema) = request.schema {
+            // Force a call to a tool taking the schema as input
+            request_body["tools"] = serde_json::json!([{
+                "name": schema.name,
+                "description": schema.description,
+                "input_schema": schema.schema
+            }]);
+            request_body["tool_choice"] = serde_json::json!({
+                "type": "tool",
+                "name": schema.name
+            });
+        }
 
-        // Make synchronous HTTP POST request to Anthropic API
-        let client = reqwest::blocking::Client::new();
-        let response = client
-            .post("https://api.anthropic.com/v1/complete")
+        // Make asynchronous HTTP POST request to Anthropic API
+        let mut http_request = self.client.post(MESSAGES_URL);
+        if !stream {
+            http_request = http_request.timeout(self.timeout);
+        }
+        let response = http_request
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", "2023-06-01")
             .json(&request_body)
-            .send()?;
+            .send()
+            .await?;
+
+        // Check if the request was successful
+        if !response.status().is_success() {
+            return Err(ProviderError::from_response(response).await);
+        }
+
+        Ok(response)
+    }
+
+    /// Reads token usage from a `usage` object of the API
+    ///
+    /// # Arguments
+    /// * `usage` - The `usage` field of a message or stream event
+    fn usage(usage: &serde_json::Value) -> Usage {
+        Usage {
+            prompt_tokens: usage["input_tokens"].as_u64().unwrap_or_default(),
+            completion_tokens: usage["output_tokens"].as_u64().unwrap_or_default(),
+        }
+    }
+}
+
+#[async_trait]
+impl AIProvider for AnthropicProvider {
+    /// Returns the provider type followed by the model
+    fn name(&self) -> String {
+        format!("anthropic:{}", self.model)
+    }
+
+    /// Returns the provider, model and parameters sent for a task
+    fn cache_identity(&self, params: &GenerationParams) -> String {
+        request_identity(&self.name(), &self.params.merge(params))
+    }
 
-        // Parse the response
-        let response_json: serde_json::Value = response.json()?;
+    /// Generates a response from the Anthropic API for the given request
+    ///
+    /// # Arguments
+    /// * `request` - The prompt and task parameters to send to the model
+    ///
+    /// # Returns
+    /// * `Ok(Completion)` - The generated response text and token usage
+    /// * `Err(ProviderError)` - If the API request fails after all retries
+    async fn generate_response(
+        &self,
+        request: &CompletionRequest<'_>,
+    ) -> Result<Completion, ProviderError> {
+        self.retry
+            .run(|| async {
+                let response = self.send(request, false).await?;
 
-        // Extract the completion text
-        let completion = response_json["completion"]
-            .as_str()
-            .ok_or("Failed to get completion text")?
-            .to_string();
+                // Parse the response
+                let response_json: serde_json::Value = response.json().await?;
+
+                // Extract the text of the first content block, or the input
+                // of the forced tool call when a schema was requested
+                let block = &response_json["content"][0];
+                let completion = match block["type"].as_str() {
+                    Some("tool_use") => block["input"].to_string(),
+                    _ => block["text"]
+                        .as_str()
+                        .ok_or_else(|| {
+                            ProviderError::MalformedResponse(
+                                "Failed to get completion text".to_string(),
+                            )
+                        })?
+                        .to_string(),
+                };
+
+                Ok(Completion::new(
+                    completion,
+                    &self.model,
+                    Self::usage(&response_json["usage"]),
+                ))
+            })
+            .await
+    }
+
+    /// Streams a response from the Anthropic API as server-sent events
+    ///
+    /// # Arguments
+    /// * `request` - The prompt and task parameters to send to the model
+    /// * `on_token` - Callback receiving each text delta
+    ///
+    /// # Returns
+    /// * `Ok(Completion)` - The complete generated response text and token usage
+    /// * `Err(ProviderError)` - If the API request fails after all retries
+    async fn generate_stream(
+        &self,
+        request: &CompletionRequest<'_>,
+        on_token: TokenSink<'_>,
+    ) -> Result<Completion, ProviderError> {
+        if !self.stream {
+            let completion = self.generate_response(request).await?;
+            on_token(&completion.text);
+            return Ok(completion);
+        }
+
+        let mut response = self.retry.run(|| self.send(request, true)).await?;
+        let mut lines = LineBuffer::new();
+        let mut completion = String::new();
+        let mut usage = Usage::default();
+
+        while let Some(chunk) = response.chunk().await? {
+            for line in lines.push(&chunk) {
+                let Some(data) = sse_data(&line) else {
+                    continue;
+                };
+                let event: serde_json::Value = serde_json::from_str(data)?;
+
+                match event["type"].as_str() {
+                    Some("message_start") => {
+                        usage = Self::usage(&event["message"]["usage"]);
+                    }
+                    Some("message_delta") => {
+                        usage.completion_tokens =
+                            event["usage"]["output_tokens"].as_u64().unwrap_or_default();
+                    }
+                    Some("content_block_delta") => {
+                        // Text deltas, or tool input deltas when a schema was requested
+                        let delta = &event["delta"];
+                        if let Some(text) =
+                            delta["text"].as_str().or(delta["partial_json"].as_str())
+                        {
+                            on_token(text);
+                            completion.push_str(text);
+                        }
+                    }
+                    Some("error") => {
+                        return Err(ProviderError::Stream(
+                            event["error"]["message"]
+                                .as_str()
+                                .unwrap_or("unknown error")
+                                .to_string(),
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(Completion::new(completion, &self.model, usage))
+    }
+
+    /// Lists the models available to the API key
+    ///
+    /// # Returns
+    /// * `Ok(Some(Vec<String>))` - The identifiers of the available models
+    /// * `Err(ProviderError)` - If the API request fails after all retries
+    async fn models(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.retry
+            .run(|| async {
+                let response = self
+                    .client
+                    .get(MODELS_URL)
+                    .timeout(self.timeout)
+                    .header("x-api-key", &self.api_key)
+                    .header("anthropic-version", "2023-06-01")
+                    .send()
+                    .await?;
+                if !response.status().is_success() {
+                    return Err(ProviderError::from_response(response).await);
+                }
 
-        Ok(completion)
+                let response_json: serde_json::Value = response.json().await?;
+                let models = response_json["data"]
+                    .as_array()
+                    .ok_or_else(|| {
+                        ProviderError::MalformedResponse("Failed to get model list".to_string())
+                    })?
+                    .iter()
+                    .filter_map(|model| model["id"].as_str().map(String::from))
+                    .collect();
+                Ok(Some(models))
+            })
+            .await
     }
 }
@@ -0,0 +1,273 @@
+use super::retry::RetryPolicy;
+use super::{request_identity, AIProvider, Completion, CompletionRequest, ProviderError, Usage};
+use crate::config::{GenerationParams, RequestConfig};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Request written as JSON to the plugin's standard input
+#[derive(Serialize)]
+struct CommandRequest<'a> {
+    /// The input prompt to send to the model
+    prompt: &'a str,
+    /// The model identifier configured for this provider
+    model: &'a str,
+    /// Generation parameters forwarded to the plugin
+    params: GenerationParams,
+    /// JSON schema the response should conform to, if the caller expects one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schema: Option<&'a serde_json::Value>,
+}
+
+/// Response read as JSON from the plugin's standard output
+#[derive(Deserialize)]
+struct CommandResponse {
+    /// The generated response text
+    response: String,
+    /// Tokens consumed by the plugin, if it reports them
+    #[serde(default)]
+    usage: Usage,
+}
+
+/// Provider implementation that delegates generation to an external executable
+///
+/// The executable receives a JSON request on stdin and must print a JSON
+/// object of the form `{"response": "..."}` on stdout, optionally with a
+/// `usage` object holding `prompt_tokens` and `completion_tokens`.
+pub struct CommandProvider {
+    /// The model identifier passed through to the plugin
+    model: String,
+    /// Path or name of the executable to spawn
+    program: String,
+    /// Extra arguments passed to the executable
+    args: Vec<String>,
+    /// Generation parameters of this provider, before task overrides
+    params: GenerationParams,
+    /// Retry strategy applied to timed out runs
+    retry: RetryPolicy,
+    /// Maximum duration of a single run of the executable
+    timeout: Duration,
+}
+
+impl CommandProvider {
+    /// Creates a new instance of the command provider
+    ///
+    /// # Arguments
+    /// * `model` - The model identifier passed to the plugin
+    /// * `program` - The executable to spawn for each request
+    /// * `args` - Extra arguments passed to the executable
+    /// * `params` - Generation parameters forwarded to the plugin
+    /// * `request` - Timeout and retry settings
+    pub fn new(
+        model: &str,
+        program: &str,
+        args: &[String],
+        params: GenerationParams,
+        request: &RequestConfig,
+    ) -> Self {
+        Self {
+            model: model.to_string(),
+            program: program.to_string(),
+            args: args.to_vec(),
+            params,
+            retry: RetryPolicy::new(request),
+            timeout: Duration::from_secs(request.timeout_secs),
+        }
+    }
+
+    /// Runs the executable once and parses its response
+    ///
+    /// # Arguments
+    /// * `request_body` - The serialized JSON request
+    async fn run(&self, request_body: &[u8]) -> Result<Completion, ProviderError> {
+        // Spawn the plugin with piped standard streams
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| {
+                ProviderError::Command(format!("Failed to spawn command '{}': {}", self.program, e))
+            })?;
+
+        // Write the request while reading the output, so neither side blocks on a
+        // full pipe, and close stdin so the plugin sees EOF
+        let stdin = child.stdin.take();
+        let write = async move {
+            if let Some(mut stdin) = stdin {
+                stdin.write_all(request_body).await?;
+            }
+            Ok::<(), std::io::Error>(())
+        };
+        let (written, output) = tokio::time::timeout(self.timeout, async {
+            tokio::join!(write, child.wait_with_output())
+        })
+        .await
+        .map_err(|_| ProviderError::Timeout)?;
+        let output = output.map_err(|e| ProviderError::Command(e.to_string()))?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        // Check if the plugin exited successfully
+        if !output.status.success() {
+            return Err(ProviderError::Command(format!(
+                "Command '{}' failed with {}: {}",
+                self.program,
+                output.status,
+                stderr.trim()
+            )));
+        }
+
+        // A plugin exiting before reading the whole request never saw the prompt
+        if let Err(e) = written {
+            return Err(ProviderError::Command(format!(
+                "Failed to write the request to command '{}' ({}): {}",
+                self.program,
+                e,
+                stderr.trim()
+            )));
+        }
+
+        // Parse the response
+        let response: CommandResponse = serde_json::from_slice(&output.stdout).map_err(|e| {
+            ProviderError::MalformedResponse(format!(
+                "Command '{}' returned invalid JSON ({}): {}",
+                self.program,
+                e,
+                stderr.trim()
+            ))
+        })?;
+
+        Ok(Completion::new(
+            response.response,
+            &self.model,
+            response.usage,
+        ))
+    }
+}
+
+#[async_trait]
+impl AIProvider for CommandProvider {
+    /// Returns the provider type followed by the model
+    fn name(&self) -> String {
+        format!("command:{}", self.model)
+    }
+
+    /// Returns the provider, model and parameters sent for a task
+    fn cache_identity(&self, params: &GenerationParams) -> String {
+        request_identity(&self.name(), &self.params.merge(params))
+    }
+
+    /// Generates a response by running the configured executable
+    ///
+    /// # Arguments
+    /// * `request` - The prompt and task parameters to send to the plugin
+    ///
+    /// # Returns
+    /// * `Ok(Completion)` - The generated response text and reported token usage
+    /// * `Err(ProviderError)` - If the plugin fails, times out or returns invalid JSON
+    async fn generate_response(
+        &self,
+        request: &CompletionRequest<'_>,
+    ) -> Result<Completion, ProviderError> {
+        // Create the request body
+        let request_body = serde_json::to_vec(&CommandRequest {
+            prompt: request.prompt,
+            model: &self.model,
+            params: self.params.merge(request.params),
+            schema: request.schema.map(|schema| &schema.schema),
+        })?;
+
+        self.retry.run(|| self.run(&request_body)).await
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// Request large enough to fill the stdin pipe of the plugin
+    const LARGE_REQUEST: usize = 1 << 20;
+
+    fn provider(script: &str) -> CommandProvider {
+        let request = RequestConfig {
+            timeout_secs: 2,
+            max_retries: 0,
+            ..RequestConfig::default()
+        };
+        CommandProvider::new(
+            "test",
+            "sh",
+            &["-c".to_string(), script.to_string()],
+            GenerationParams::default(),
+            &request,
+        )
+    }
+
+    #[tokio::test]
+    async fn run_returns_the_plugin_response() {
+        let provider = provider(r#"cat >/dev/null; printf '{"response": "feat: add"}'"#);
+        let completion = provider.run(b"{}").await.unwrap();
+        assert_eq!(completion.text, "feat: add");
+    }
+
+    #[tokio::test]
+    async fn generate_response_sends_the_documented_request() {
+        // The plugin answers with the request it received, escaped as a JSON string
+        let provider = provider(
+            r#"request=$(sed 's/\\/\\\\/g; s/"/\\"/g'); printf '{"response": "%s"}' "$request""#,
+        );
+        let params = GenerationParams {
+            temperature: Some(0.2),
+            ..GenerationParams::default()
+        };
+        let completion = provider
+            .generate_response(&CompletionRequest::new("describe the diff", &params))
+            .await
+            .unwrap();
+
+        let request: serde_json::Value = serde_json::from_str(&completion.text).unwrap();
+        assert_eq!(
+            request,
+            serde_json::json!({
+                "prompt": "describe the diff",
+                "model": "test",
+                "params": {"temperature": 0.2},
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn run_reads_output_while_writing_the_request() {
+        // The plugin fills its stdout before reading stdin
+        let provider = provider(
+            r#"printf '{"response": "'; head -c 200000 /dev/zero | tr '\0' x; printf '"}'; cat >/dev/null"#,
+        );
+        let completion = provider.run(&vec![b' '; LARGE_REQUEST]).await.unwrap();
+        assert_eq!(completion.text.len(), 200_000);
+    }
+
+    #[tokio::test]
+    async fn run_times_out_when_the_plugin_never_reads_stdin() {
+        let provider = provider("sleep 10");
+        let result = provider.run(&vec![b' '; LARGE_REQUEST]).await;
+        assert!(matches!(result, Err(ProviderError::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn run_keeps_stderr_when_the_request_cannot_be_written() {
+        let provider = provider("echo 'no input expected' >&2");
+        let error = provider.run(&vec![b' '; LARGE_REQUEST]).await.unwrap_err();
+        let message = error.to_string();
+        assert!(
+            message.contains("Failed to write the request"),
+            "{}",
+            message
+        );
+        assert!(message.contains("no input expected"), "{}", message);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::config::GenerationParams;
+use serde::{Deserialize, Serialize};
+use std::ops::AddAssign;
+
+/// JSON schema that a structured response must conform to
+#[derive(Clone, Debug)]
+pub struct OutputSchema {
+    /// Identifier of the schema, used as tool or format name
+    pub name: String,
+    /// What the structured response represents
+    pub description: String,
+    /// The JSON schema of the response object
+    pub schema: serde_json::Value,
+}
+
+impl OutputSchema {
+    /// Creates an output schema
+    ///
+    /// # Arguments
+    /// * `name` - Identifier of the schema
+    /// * `description` - What the structured response represents
+    /// * `schema` - The JSON schema of the response object
+    pub fn new(name: &str, description: &str, schema: serde_json::Value) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            schema,
+        }
+    }
+}
+
+/// A generation request sent to a provider
+#[derive(Clone, Copy, Debug)]
+pub struct CompletionRequest<'a> {
+    /// The input text to send to the model
+    pub prompt: &'a str,
+    /// Task parameters, overriding the provider's own parameters
+    pub params: &'a GenerationParams,
+    /// Schema of the expected JSON response, for providers supporting structured output
+    pub schema: Option<&'a OutputSchema>,
+}
+
+impl<'a> CompletionRequest<'a> {
+    /// Creates a request for the given prompt
+    ///
+    /// # Arguments
+    /// * `prompt` - The input text to send to the model
+    /// * `params` - Task parameters, overriding the provider's own parameters
+    pub fn new(prompt: &'a str, params: &'a GenerationParams) -> Self {
+        Self {
+            prompt,
+            params,
+            schema: None,
+        }
+    }
+
+    /// Requests a JSON response conforming to the given schema
+    ///
+    /// Providers without structured output ignore the schema, so the response
+    /// must still be parsed leniently.
+    ///
+    /// # Arguments
+    /// * `schema` - Schema of the expected JSON response
+    pub fn with_schema(mut self, schema: Option<&'a OutputSchema>) -> Self {
+        self.schema = schema;
+        self
+    }
+}
+
+/// Tokens consumed by one or more requests
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Usage {
+    /// Tokens of the prompt sent to the model
+    pub prompt_tokens: u64,
+    /// Tokens generated by the model
+    pub completion_tokens: u64,
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+    }
+}
+
+/// A response generated by a provider
+#[derive(Clone, Debug)]
+pub struct Completion {
+    /// The generated response text
+    pub text: String,
+    /// The model that generated the response
+    pub model: String,
+    /// Tokens consumed by the request, when reported by the provider
+    pub usage: Usage,
+}
+
+impl Completion {
+    /// Creates a completion for the given model
+    ///
+    /// # Arguments
+    /// * `text` - The generated response text
+    /// * `model` - The model that generated the response
+    /// * `usage` - Tokens consumed by the request
+    pub fn new(text: String, model: &str, usage: Usage) -> Self {
+        Self {
+            text,
+            model: model.to_string(),
+            usage,
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Represents errors that can occur while requesting a provider
+#[derive(Error, Debug)]
+pub enum ProviderError {
+    /// The provider rejected the credentials
+    #[error("Authentication failed ({status}): {message}")]
+    Auth { status: u16, message: String },
+
+    /// The provider is throttling requests
+    #[error("Rate limit exceeded: {message}")]
+    RateLimit {
+        retry_after: Option<Duration>,
+        message: String,
+    },
+
+    /// The provider failed or is overloaded
+    #[error("Server error ({status}): {message}")]
+    Server {
+        status: u16,
+        retry_after: Option<Duration>,
+        message: String,
+    },
+
+    /// The provider rejected the request itself
+    #[error("Request rejected ({status}): {message}")]
+    Request { status: u16, message: String },
+
+    /// The request did not complete within the configured timeout
+    #[error("Request timed out")]
+    Timeout,
+
+    /// The provider could not be reached
+    #[error("Network error: {0}")]
+    Network(String),
+
+    /// The provider answered with a body that could not be understood
+    #[error("Malformed response: {0}")]
+    MalformedResponse(String),
+
+    /// The provider reported an error in the middle of a streamed response
+    #[error("Stream interrupted: {0}")]
+    Stream(String),
+
+    /// The per-run budget is spent, so no further request is sent
+    #[error("Budget exceeded: spent ${spent:.4} of ${budget:.4}")]
+    BudgetExceeded { spent: f64, budget: f64 },
+
+    /// The external command provider failed
+    #[error("Command error: {0}")]
+    Command(String),
+}
+
+impl ProviderError {
+    /// Builds an error from an unsuccessful HTTP response
+    ///
+    /// # Arguments
+    /// * `response` - The response with a non-success status
+    ///
+    /// # Returns
+    /// The error variant matching the status code, with the body as message
+    pub async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status().as_u16();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, Utc::now()));
+        let message = response.text().await.unwrap_or_default();
+
+        match status {
+            401 | 403 => Self::Auth { status, message },
+            408 => Self::Timeout,
+            429 => Self::RateLimit {
+                retry_after,
+                message,
+            },
+            500..=599 => Self::Server {
+                status,
+                retry_after,
+                message,
+            },
+            _ => Self::Request { status, message },
+        }
+    }
+
+    /// Whether the failure is transient and the request may be retried
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::RateLimit { .. } | Self::Server { .. } | Self::Timeout | Self::Network(_)
+        )
+    }
+
+    /// Delay requested by the provider before retrying, if any
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimit { retry_after, .. } | Self::Server { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+/// Parses a `Retry-After` header, given in seconds or as an HTTP date
+///
+/// # Arguments
+/// * `value` - The value of the header
+/// * `now` - The current time, from which the delay until a date is measured
+///
+/// # Returns
+/// The delay to wait, zero for a date in the past, or `None` if the value is invalid
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    // The preferred format, then the obsolete RFC 850 and asctime formats
+    let date = DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+        .or_else(|| {
+            ["%A, %d-%b-%y %H:%M:%S GMT", "%a %b %e %H:%M:%S %Y"]
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+                .map(|date| date.and_utc())
+        })?;
+    Some((date - now).to_std().unwrap_or(Duration::ZERO))
+}
+
+impl From<reqwest::Error> for ProviderError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            Self::Timeout
+        } else if error.is_decode() {
+            Self::MalformedResponse(error.to_string())
+        } else {
+            Self::Network(error.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for ProviderError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::MalformedResponse(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("1994-11-06T08:49:07Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn parse_retry_after_reads_seconds() {
+        assert_eq!(
+            parse_retry_after(" 120 ", now()),
+            Some(Duration::from_secs(120))
+        );
+    }
+
+    #[test]
+    fn parse_retry_after_reads_http_dates() {
+        let expected = Some(Duration::from_secs(30));
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT", now()),
+            expected
+        );
+        assert_eq!(
+            parse_retry_after("Sunday, 06-Nov-94 08:49:37 GMT", now()),
+            expected
+        );
+        assert_eq!(
+            parse_retry_after("Sun Nov  6 08:49:37 1994", now()),
+            expected
+        );
+    }
+
+    #[test]
+    fn parse_retry_after_waits_no_time_for_past_dates() {
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:00:00 GMT", now()),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn parse_retry_after_rejects_invalid_values() {
+        assert_eq!(parse_retry_after("soon", now()), None);
+        assert_eq!(parse_retry_after("-5", now()), None);
+    }
+}
@@ -0,0 +1,126 @@
+use super::{AIProvider, Completion, CompletionRequest, ProviderError, TokenSink};
+use crate::config::GenerationParams;
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Composite provider trying a chain of providers in priority order
+///
+/// The next provider is only tried when the previous one fails with a
+/// retryable error (rate limit, server error, timeout or network failure),
+/// once its own retries are exhausted.
+pub struct FallbackProvider {
+    /// Providers in priority order, with a label used for logging
+    providers: Vec<(String, Arc<dyn AIProvider>)>,
+}
+
+impl FallbackProvider {
+    /// Creates a new fallback chain
+    ///
+    /// # Arguments
+    /// * `providers` - Labelled providers in priority order
+    pub fn new(providers: Vec<(String, Arc<dyn AIProvider>)>) -> Self {
+        Self { providers }
+    }
+
+    /// Decides whether the chain moves on to the next provider after an error
+    ///
+    /// # Arguments
+    /// * `label` - The provider that failed
+    /// * `index` - Position of the provider in the chain
+    /// * `error` - The error returned by the provider
+    fn should_fall_back(&self, label: &str, index: usize, error: &ProviderError) -> bool {
+        let has_next = index + 1 < self.providers.len();
+        if has_next && error.is_retryable() {
+            warn!("provider {} failed ({}), falling back", label, error);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[async_trait]
+impl AIProvider for FallbackProvider {
+    /// Returns the names of the chained providers, in priority order
+    fn name(&self) -> String {
+        self.providers
+            .iter()
+            .map(|(label, _)| label.as_str())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Returns the identities of the chained providers, in priority order
+    fn cache_identity(&self, params: &GenerationParams) -> String {
+        self.providers
+            .iter()
+            .map(|(_, provider)| provider.cache_identity(params))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Generates a response from the first provider of the chain that answers
+    ///
+    /// # Arguments
+    /// * `request` - The prompt and task parameters to send to the model
+    ///
+    /// # Returns
+    /// * `Ok(Completion)` - The generated response text and token usage
+    /// * `Err(ProviderError)` - The error of the last provider tried
+    async fn generate_response(
+        &self,
+        request: &CompletionRequest<'_>,
+    ) -> Result<Completion, ProviderError> {
+        for (index, (label, provider)) in self.providers.iter().enumerate() {
+            match provider.generate_response(request).await {
+                Ok(response) => {
+                    info!("response generated by {}", label);
+                    return Ok(response);
+                }
+                Err(e) if self.should_fall_back(label, index, &e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Err(ProviderError::Network("No provider configured".to_string()))
+    }
+
+    /// Streams a response from the first provider of the chain that answers
+    ///
+    /// A provider failing after it started streaming is not replaced, since
+    /// part of its response has already been emitted.
+    ///
+    /// # Arguments
+    /// * `request` - The prompt and task parameters to send to the model
+    /// * `on_token` - Callback receiving each piece of generated text
+    ///
+    /// # Returns
+    /// * `Ok(Completion)` - The complete generated response text and token usage
+    /// * `Err(ProviderError)` - The error of the last provider tried
+    async fn generate_stream(
+        &self,
+        request: &CompletionRequest<'_>,
+        on_token: TokenSink<'_>,
+    ) -> Result<Completion, ProviderError> {
+        for (index, (label, provider)) in self.providers.iter().enumerate() {
+            let streamed = AtomicBool::new(false);
+            let forward = |token: &str| {
+                streamed.store(true, Ordering::Relaxed);
+                on_token(token);
+            };
+
+            match provider.generate_stream(request, &forward).await {
+                Ok(response) => {
+                    info!("response generated by {}", label);
+                    return Ok(response);
+                }
+                Err(e)
+                    if !streamed.load(Ordering::Relaxed)
+                        && self.should_fall_back(label, index, &e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Err(ProviderError::Network("No provider configured".to_string()))
+    }
+}
@@ -1,48 +1,197 @@
+use crate::config::{Config, GenerationParams, ProviderEntry, RequestConfig};
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
 use tracing::info;
 
+pub use completion::{Completion, CompletionRequest, OutputSchema, Usage};
+pub use error::ProviderError;
+
+/// Callback receiving generated text as it is streamed by the provider
+pub type TokenSink<'a> = &'a (dyn Fn(&str) + Send + Sync);
+
 /// Common trait that must be implemented by all AI providers
-pub trait AIProvider {
-    /// Generates a response from the AI model for the given prompt
+///
+/// Providers are shared across concurrent tasks, so implementations keep a
+/// single pooled HTTP client instead of creating one per request.
+#[async_trait]
+pub trait AIProvider: Send + Sync {
+    /// Identifies the provider and model, e.g. "openai:gpt-4o"
+    fn name(&self) -> String;
+
+    /// Identifies the requests sent for a task, as part of response cache keys
+    ///
+    /// Covers the model and the parameters actually sent, the provider's own
+    /// merged with those of the task, so a response is not reused once they change.
+    ///
+    /// # Arguments
+    /// * `params` - Task parameters, overriding the provider's own parameters
+    fn cache_identity(&self, params: &GenerationParams) -> String;
+
+    /// Generates a response from the AI model for the given request
     ///
     /// # Arguments
-    /// * `prompt` - The input text to send to the model
+    /// * `request` - The prompt and task parameters to send to the model
     ///
     /// # Returns
-    /// * `Ok(String)` - The generated response text
-    /// * `Err(Box<dyn Error>)` - If the request fails
-    fn generate_response(&self, prompt: &str) -> Result<String, Box<dyn std::error::Error>>;
+    /// * `Ok(Completion)` - The generated response text and token usage
+    /// * `Err(ProviderError)` - If the request fails after all retries
+    async fn generate_response(
+        &self,
+        request: &CompletionRequest<'_>,
+    ) -> Result<Completion, ProviderError>;
+
+    /// Generates a response, passing text to `on_token` as soon as it is received
+    ///
+    /// Providers without streaming support, or with streaming disabled, emit the
+    /// complete response as a single token.
+    ///
+    /// # Arguments
+    /// * `request` - The prompt and task parameters to send to the model
+    /// * `on_token` - Callback receiving each piece of generated text
+    ///
+    /// # Returns
+    /// * `Ok(Completion)` - The complete generated response text and token usage
+    /// * `Err(ProviderError)` - If the request fails after all retries
+    async fn generate_stream(
+        &self,
+        request: &CompletionRequest<'_>,
+        on_token: TokenSink<'_>,
+    ) -> Result<Completion, ProviderError> {
+        let completion = self.generate_response(request).await?;
+        on_token(&completion.text);
+        Ok(completion)
+    }
+
+    /// Lists the models the provider serves
+    ///
+    /// Providers without a model listing return `None`.
+    ///
+    /// # Returns
+    /// * `Ok(Some(Vec<String>))` - The identifiers of the available models
+    /// * `Ok(None)` - If the provider cannot list its models
+    /// * `Err(ProviderError)` - If the request fails after all retries
+    async fn models(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        Ok(None)
+    }
 }
 
 pub mod anthropic;
+pub mod command;
+mod completion;
+mod error;
+mod fallback;
 pub mod ollama;
 pub mod openai;
+mod retry;
+mod stream;
+
+/// Identifies the requests of a single provider, see `AIProvider::cache_identity`
+///
+/// # Arguments
+/// * `name` - The provider and model
+/// * `params` - The parameters sent with the requests
+fn request_identity(name: &str, params: &GenerationParams) -> String {
+    format!(
+        "{} {}",
+        name,
+        serde_json::to_string(params).unwrap_or_default()
+    )
+}
+
+/// Builds the HTTP client shared by all requests of a provider
+///
+/// # Arguments
+/// * `request` - Timeout settings applied to the connection and reads
+///
+/// # Returns
+/// * `reqwest::Client` - A pooled client with the configured timeouts
+fn build_client(request: &RequestConfig) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(request.connect_timeout_secs))
+        .read_timeout(Duration::from_secs(request.read_timeout_secs))
+        .build()
+        .unwrap_or_default()
+}
 
 /// Creates a new AI provider instance based on the specified type
 ///
+/// Unlike `create_provider`, fallback providers are not involved, so a single
+/// entry of the chain can be checked on its own.
+///
 /// # Arguments
-/// * `provider_type` - The type of provider to create ("openai", "anthropic", or "ollama")
-/// * `config` - Application configuration containing provider settings
+/// * `entry` - The provider type, model and credentials to use
+/// * `config` - Application configuration containing shared provider settings
 ///
 /// # Returns
-/// * `Box<dyn AIProvider>` - The created provider instance
+/// * `Arc<dyn AIProvider>` - The created provider instance, shareable across tasks
 ///
 /// # Panics
 /// * If an unknown provider type is specified
-pub fn create_provider(provider_type: &str, config: &crate::config::Config) -> Box<dyn AIProvider> {
-    info!("creating provider: {}", provider_type);
-    match provider_type {
-        "openai" => Box::new(openai::OpenAIProvider::new(
-            &config.model,
-            &config.openai_api_key,
+pub fn build_provider(entry: &ProviderEntry, config: &Config) -> Arc<dyn AIProvider> {
+    info!("creating provider: {} ({})", entry.provider, entry.model);
+    let api_key = entry.api_key.as_deref().unwrap_or_default();
+    let params = config.generation.for_provider(&entry.provider);
+    match entry.provider.as_str() {
+        "openai" => Arc::new(openai::OpenAIProvider::new(
+            &entry.model,
+            api_key,
+            config.stream,
+            params,
+            &config.request,
         )),
-        "anthropic" => Box::new(anthropic::AnthropicProvider::new(
-            &config.model,
-            &config.anthropic_api_key,
+        "anthropic" => Arc::new(anthropic::AnthropicProvider::new(
+            &entry.model,
+            api_key,
+            config.stream,
+            params,
+            &config.request,
         )),
-        "ollama" => Box::new(ollama::OllamaProvider::new(
-            &config.model,
-            &config.ollama_url,
+        "ollama" => Arc::new(ollama::OllamaProvider::new(
+            &entry.model,
+            entry.url.as_deref().unwrap_or(&config.ollama_url),
+            config.stream,
+            params,
+            &config.request,
+        )),
+        "command" => Arc::new(command::CommandProvider::new(
+            &entry.model,
+            &config.command.program,
+            &config.command.args,
+            params,
+            &config.request,
         )),
         _ => panic!("Unknown provider type"),
     }
 }
+
+/// Creates the provider used by the application
+///
+/// When fallback providers are configured, the main provider and the fallbacks
+/// are wrapped in a `FallbackProvider` trying each of them in turn.
+///
+/// # Arguments
+/// * `config` - Application configuration containing provider settings
+/// * `model` - Model replacing the configured one for the main provider, if any
+///
+/// # Returns
+/// * `Arc<dyn AIProvider>` - The created provider instance, shareable across tasks
+///
+/// # Panics
+/// * If an unknown provider type is specified
+pub fn create_provider(config: &Config, model: Option<&str>) -> Arc<dyn AIProvider> {
+    let chain = config.provider_chain_with_model(model.unwrap_or(&config.model));
+    if chain.len() == 1 {
+        return build_provider(&chain[0], config);
+    }
+
+    Arc::new(fallback::FallbackProvider::new(
+        chain
+            .iter()
+            .map(|entry| {
+                let label = format!("{}:{}", entry.provider, entry.model);
+                (label, build_provider(entry, config))
+            })
+            .collect(),
+    ))
+}
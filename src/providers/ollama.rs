@@ -1,11 +1,29 @@
-use super::AIProvider;
+use super::retry::RetryPolicy;
+use super::stream::LineBuffer;
+use super::{
+    build_client, request_identity, AIProvider, Completion, CompletionRequest, ProviderError,
+    TokenSink, Usage,
+};
+use crate::config::{GenerationParams, RequestConfig};
+use async_trait::async_trait;
+use std::time::Duration;
 
 /// Provider implementation for Ollama's local AI models
 pub struct OllamaProvider {
     /// The model identifier to use for requests
     model: String,
+    /// Shared HTTP client reused across requests
+    client: reqwest::Client,
     /// Base URL for the Ollama API endpoint
     base_url: String,
+    /// Retry strategy applied to failed requests
+    retry: RetryPolicy,
+    /// Maximum duration of a non-streamed request
+    timeout: Duration,
+    /// Generation parameters of this provider, before task overrides
+    params: GenerationParams,
+    /// Whether responses are streamed as newline-delimited JSON
+    stream: bool,
 }
 
 impl OllamaProvider {
@@ -14,52 +32,246 @@ impl OllamaProvider {
     /// # Arguments
     /// * `model` - The model identifier to use
     /// * `base_url` - Base URL for the Ollama API
-    pub fn new(model: &str, base_url: &str) -> Self {
+    /// * `stream` - Whether to stream responses when supported by the caller
+    /// * `params` - Generation parameters of this provider
+    /// * `request` - Timeout and retry settings
+    pub fn new(
+        model: &str,
+        base_url: &str,
+        stream: bool,
+        params: GenerationParams,
+        request: &RequestConfig,
+    ) -> Self {
         Self {
             model: model.to_string(),
+            client: build_client(request),
             base_url: base_url.to_string(),
+            retry: RetryPolicy::new(request),
+            timeout: Duration::from_secs(request.timeout_secs),
+            params,
+            stream,
         }
     }
-}
 
-impl AIProvider for OllamaProvider {
-    /// Generates a response from the Ollama API for the given prompt
+    /// Sends a generate request
     ///
     /// # Arguments
-    /// * `prompt` - The input prompt to send to the model
-    ///
-    /// # Returns
-    /// * `Ok(String)` - The generated response text
-    /// * `Err(Box<dyn Error>)` - If the API request fails
-    fn generate_response(&self, prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
+    /// * `request` - The prompt and task parameters to send to the model
+    /// * `stream` - Whether to request a newline-delimited JSON stream
+    async fn send(
+        &self,
+        request: &CompletionRequest<'_>,
+        stream: bool,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let params = self.params.merge(request.params);
+
         // Create the request body
-        let request_body = serde_json::json!({
+        let mut request_body = serde_json::json!({
             "model": self.model,
-            "prompt": prompt,
-            "stream": false
+            "prompt": request.prompt,
+            "stream": stream
         });
 
-        // Make synchronous HTTP POST request to Ollama API
-        let client = reqwest::blocking::Client::new();
-        let response = client
-            .post(format!("{}/api/generate", self.base_url))
-            .json(&request_body)
-            .send()?;
+        // Sampling parameters are passed as model options
+        let mut options = serde_json::Map::new();
+        if let Some(temperature) = params.temperature {
+            options.insert("temperature".to_string(), serde_json::json!(temperature));
+        }
+        if let Some(max_tokens) = params.max_tokens {
+            options.insert("num_predict".to_string(), serde_json::json!(max_tokens));
+        }
+        if let Some(top_p) = params.top_p {
+            options.insert("top_p".to_string(), serde_json::json!(top_p));
+        }
+        if !params.stop.is_empty() {
+            options.insert("stop".to_string(), serde_json::json!(params.stop));
+        }
+        if !options.is_empty() {
+            request_body["options"] = serde_json::Value::Object(options);
+        }
+        if let Some(schema) = request.schema {
+            // Constrain the response to the schema
+            request_body["format"] = schema.schema.clone();
+        }
+
+        // Make asynchronous HTTP POST request to Ollama API
+        let mut http_request = self.client.post(format!("{}/api/generate", self.base_url));
+        if !stream {
+            http_request = http_request.timeout(self.timeout);
+        }
+        let response = http_request.json(&request_body).send().await?;
 
         // Check if the request was successful
         if !response.status().is_success() {
-            return Err(format!("API request failed with status: {}", response.status()).into());
+            return Err(ProviderError::from_response(response).await);
+        }
+
+        Ok(response)
+    }
+
+    /// Reads token usage from the counters of a final response object
+    ///
+    /// # Arguments
+    /// * `response` - A response object of `/api/generate`
+    fn usage(response: &serde_json::Value) -> Usage {
+        Usage {
+            prompt_tokens: response["prompt_eval_count"].as_u64().unwrap_or_default(),
+            completion_tokens: response["eval_count"].as_u64().unwrap_or_default(),
         }
+    }
 
-        // Parse the response
-        let response_json: serde_json::Value = response.json()?;
+    /// Parses a single streamed line, failing on reported errors
+    ///
+    /// # Arguments
+    /// * `line` - A JSON object emitted by `/api/generate`
+    ///
+    /// # Returns
+    /// The generated fragment, and the token usage once the stream is done
+    fn stream_event(line: &str) -> Result<(String, Option<Usage>), ProviderError> {
+        let event: serde_json::Value = serde_json::from_str(line)?;
+
+        if let Some(error) = event["error"].as_str() {
+            return Err(ProviderError::Stream(error.to_string()));
+        }
 
-        // Extract the response text with better error handling
-        let response_text = response_json["response"]
-            .as_str()
-            .ok_or("Response field not found or not a string")?
-            .to_string();
+        let text = event["response"].as_str().unwrap_or_default().to_string();
+        let usage = event["done"]
+            .as_bool()
+            .unwrap_or_default()
+            .then(|| Self::usage(&event));
+        Ok((text, usage))
+    }
+}
+
+#[async_trait]
+impl AIProvider for OllamaProvider {
+    /// Returns the provider type followed by the model
+    fn name(&self) -> String {
+        format!("ollama:{}", self.model)
+    }
+
+    /// Returns the provider, model and parameters sent for a task
+    fn cache_identity(&self, params: &GenerationParams) -> String {
+        request_identity(&self.name(), &self.params.merge(params))
+    }
+
+    /// Generates a response from the Ollama API for the given request
+    ///
+    /// # Arguments
+    /// * `request` - The prompt and task parameters to send to the model
+    ///
+    /// # Returns
+    /// * `Ok(Completion)` - The generated response text and token usage
+    /// * `Err(ProviderError)` - If the API request fails after all retries
+    async fn generate_response(
+        &self,
+        request: &CompletionRequest<'_>,
+    ) -> Result<Completion, ProviderError> {
+        self.retry
+            .run(|| async {
+                let response = self.send(request, false).await?;
+
+                // Parse the response
+                let response_json: serde_json::Value = response.json().await?;
+
+                // Extract the response text with better error handling
+                let response_text = response_json["response"]
+                    .as_str()
+                    .ok_or_else(|| {
+                        ProviderError::MalformedResponse(
+                            "Response field not found or not a string".to_string(),
+                        )
+                    })?
+                    .to_string();
+
+                Ok(Completion::new(
+                    response_text,
+                    &self.model,
+                    Self::usage(&response_json),
+                ))
+            })
+            .await
+    }
+
+    /// Streams a response from the Ollama API as newline-delimited JSON
+    ///
+    /// # Arguments
+    /// * `request` - The prompt and task parameters to send to the model
+    /// * `on_token` - Callback receiving each generated fragment
+    ///
+    /// # Returns
+    /// * `Ok(Completion)` - The complete generated response text and token usage
+    /// * `Err(ProviderError)` - If the API request fails after all retries
+    async fn generate_stream(
+        &self,
+        request: &CompletionRequest<'_>,
+        on_token: TokenSink<'_>,
+    ) -> Result<Completion, ProviderError> {
+        if !self.stream {
+            let completion = self.generate_response(request).await?;
+            on_token(&completion.text);
+            return Ok(completion);
+        }
+
+        let mut response = self.retry.run(|| self.send(request, true)).await?;
+        let mut lines = LineBuffer::new();
+        let mut response_text = String::new();
+        let mut usage = Usage::default();
+
+        let mut handle_line = |line: &str| -> Result<(), ProviderError> {
+            let (text, final_usage) = Self::stream_event(line)?;
+            on_token(&text);
+            response_text.push_str(&text);
+            if let Some(final_usage) = final_usage {
+                usage = final_usage;
+            }
+            Ok(())
+        };
+
+        while let Some(chunk) = response.chunk().await? {
+            for line in lines.push(&chunk) {
+                if !line.is_empty() {
+                    handle_line(&line)?;
+                }
+            }
+        }
+
+        if let Some(line) = lines.finish() {
+            handle_line(&line)?;
+        }
+
+        Ok(Completion::new(response_text, &self.model, usage))
+    }
+
+    /// Lists the models pulled into the local Ollama
+    ///
+    /// # Returns
+    /// * `Ok(Some(Vec<String>))` - The identifiers of the available models
+    /// * `Err(ProviderError)` - If the request fails after all retries
+    async fn models(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.retry
+            .run(|| async {
+                let response = self
+                    .client
+                    .get(format!("{}/api/tags", self.base_url))
+                    .timeout(self.timeout)
+                    .send()
+                    .await?;
+                if !response.status().is_success() {
+                    return Err(ProviderError::from_response(response).await);
+                }
 
-        Ok(response_text)
+                let response_json: serde_json::Value = response.json().await?;
+                let models = response_json["models"]
+                    .as_array()
+                    .ok_or_else(|| {
+                        ProviderError::MalformedResponse("Failed to get model list".to_string())
+                    })?
+                    .iter()
+                    .filter_map(|model| model["name"].as_str().map(String::from))
+                    .collect();
+                Ok(Some(models))
+            })
+            .await
     }
 }
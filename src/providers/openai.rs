@@ -1,11 +1,35 @@
-use super::AIProvider;
+use super::retry::RetryPolicy;
+use super::stream::{sse_data, LineBuffer};
+use super::{
+    build_client, request_identity, AIProvider, Completion, CompletionRequest, ProviderError,
+    TokenSink, Usage,
+};
+use crate::config::{GenerationParams, RequestConfig};
+use async_trait::async_trait;
+use std::time::Duration;
+
+/// Endpoint of the OpenAI Chat Completions API
+const CHAT_COMPLETIONS_URL: &str = "https://api.openai.com/v1/chat/completions";
+
+/// Endpoint listing the models of the OpenAI API
+const MODELS_URL: &str = "https://api.openai.com/v1/models";
 
 /// Provider implementation for OpenAI's language models
 pub struct OpenAIProvider {
     /// The model identifier to use for requests
     model: String,
+    /// Shared HTTP client reused across requests
+    client: reqwest::Client,
     /// API key for authentication with OpenAI
     api_key: String,
+    /// Retry strategy applied to failed requests
+    retry: RetryPolicy,
+    /// Maximum duration of a non-streamed request
+    timeout: Duration,
+    /// Generation parameters of this provider, before task overrides
+    params: GenerationParams,
+    /// Whether responses are streamed as server-sent events
+    stream: bool,
 }
 
 impl OpenAIProvider {
@@ -14,52 +38,233 @@ impl OpenAIProvider {
     /// # Arguments
     /// * `model` - The model identifier to use
     /// * `api_key` - API key for authentication
-    pub fn new(model: &str, api_key: &str) -> Self {
+    /// * `stream` - Whether to stream responses when supported by the caller
+    /// * `params` - Generation parameters of this provider
+    /// * `request` - Timeout and retry settings
+    pub fn new(
+        model: &str,
+        api_key: &str,
+        stream: bool,
+        params: GenerationParams,
+        request: &RequestConfig,
+    ) -> Self {
         Self {
             model: model.to_string(),
+            client: build_client(request),
             api_key: api_key.to_string(),
+            retry: RetryPolicy::new(request),
+            timeout: Duration::from_secs(request.timeout_secs),
+            params,
+            stream,
         }
     }
-}
 
-impl AIProvider for OpenAIProvider {
-    /// Generates a response from the OpenAI API for the given prompt
+    /// Sends a chat completion request
     ///
     /// # Arguments
-    /// * `prompt` - The input prompt to send to the model
-    ///
-    /// # Returns
-    /// * `Ok(String)` - The generated response text
-    /// * `Err(Box<dyn Error>)` - If the API request fails
-    fn generate_response(&self, prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
+    /// * `request` - The prompt and task parameters to send to the model
+    /// * `stream` - Whether to request a server-sent event stream
+    async fn send(
+        &self,
+        request: &CompletionRequest<'_>,
+        stream: bool,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let params = self.params.merge(request.params);
+
         // Create the request body
-        let request_body = serde_json::json!({
+        let mut request_body = serde_json::json!({
             "model": self.model,
             "messages": [
                 {
                     "role": "user",
-                    "content": prompt
+                    "content": request.prompt
                 }
-            ]
+            ],
+            "stream": stream
         });
+        if let Some(temperature) = params.temperature {
+            request_body["temperature"] = serde_json::json!(temperature);
+        }
+        if let Some(max_tokens) = params.max_tokens {
+            request_body["max_tokens"] = serde_json::json!(max_tokens);
+        }
+        if let Some(top_p) = params.top_p {
+            request_body["top_p"] = serde_json::json!(top_p);
+        }
+        if !params.stop.is_empty() {
+            request_body["stop"] = serde_json::json!(params.stop);
+        }
+        if let Some(schema) = request.schema {
+            // Constrain the response to the schema
+            request_body["response_format"] = serde_json::json!({
+                "type": "json_schema",
+                "json_schema": {
+                    "name": schema.name,
+                    "description": schema.description,
+                    "schema": schema.schema,
+                    "strict": true
+                }
+            });
+        }
+        if stream {
+            // Ask for a final chunk reporting token usage
+            request_body["stream_options"] = serde_json::json!({ "include_usage": true });
+        }
 
-        // Make synchronous HTTP POST request to OpenAI API
-        let client = reqwest::blocking::Client::new();
-        let response = client
-            .post("https://api.openai.com/v1/chat/completions")
+        // Make asynchronous HTTP POST request to OpenAI API
+        let mut http_request = self.client.post(CHAT_COMPLETIONS_URL);
+        if !stream {
+            http_request = http_request.timeout(self.timeout);
+        }
+        let response = http_request
             .header("Authorization", format!("Bearer {}", self.api_key))
             .json(&request_body)
-            .send()?;
+            .send()
+            .await?;
+
+        // Check if the request was successful
+        if !response.status().is_success() {
+            return Err(ProviderError::from_response(response).await);
+        }
+
+        Ok(response)
+    }
+
+    /// Reads token usage from a `usage` object of the API
+    ///
+    /// # Arguments
+    /// * `usage` - The `usage` field of a response or final stream chunk
+    fn usage(usage: &serde_json::Value) -> Usage {
+        Usage {
+            prompt_tokens: usage["prompt_tokens"].as_u64().unwrap_or_default(),
+            completion_tokens: usage["completion_tokens"].as_u64().unwrap_or_default(),
+        }
+    }
+}
 
-        // Parse the response
-        let response_json: serde_json::Value = response.json()?;
+#[async_trait]
+impl AIProvider for OpenAIProvider {
+    /// Returns the provider type followed by the model
+    fn name(&self) -> String {
+        format!("openai:{}", self.model)
+    }
 
-        // Extract the response text from the first choice
-        let response_text = response_json["choices"][0]["message"]["content"]
-            .as_str()
-            .ok_or("Failed to get response text")?
-            .to_string();
+    /// Returns the provider, model and parameters sent for a task
+    fn cache_identity(&self, params: &GenerationParams) -> String {
+        request_identity(&self.name(), &self.params.merge(params))
+    }
+
+    /// Generates a response from the OpenAI API for the given request
+    ///
+    /// # Arguments
+    /// * `request` - The prompt and task parameters to send to the model
+    ///
+    /// # Returns
+    /// * `Ok(Completion)` - The generated response text and token usage
+    /// * `Err(ProviderError)` - If the API request fails after all retries
+    async fn generate_response(
+        &self,
+        request: &CompletionRequest<'_>,
+    ) -> Result<Completion, ProviderError> {
+        self.retry
+            .run(|| async {
+                let response = self.send(request, false).await?;
+
+                // Parse the response
+                let response_json: serde_json::Value = response.json().await?;
+
+                // Extract the response text from the first choice
+                let response_text = response_json["choices"][0]["message"]["content"]
+                    .as_str()
+                    .ok_or_else(|| {
+                        ProviderError::MalformedResponse("Failed to get response text".to_string())
+                    })?
+                    .to_string();
+
+                Ok(Completion::new(
+                    response_text,
+                    &self.model,
+                    Self::usage(&response_json["usage"]),
+                ))
+            })
+            .await
+    }
+
+    /// Streams a response from the OpenAI API as server-sent events
+    ///
+    /// # Arguments
+    /// * `request` - The prompt and task parameters to send to the model
+    /// * `on_token` - Callback receiving each content delta
+    ///
+    /// # Returns
+    /// * `Ok(Completion)` - The complete generated response text and token usage
+    /// * `Err(ProviderError)` - If the API request fails after all retries
+    async fn generate_stream(
+        &self,
+        request: &CompletionRequest<'_>,
+        on_token: TokenSink<'_>,
+    ) -> Result<Completion, ProviderError> {
+        if !self.stream {
+            let completion = self.generate_response(request).await?;
+            on_token(&completion.text);
+            return Ok(completion);
+        }
+
+        let mut response = self.retry.run(|| self.send(request, true)).await?;
+        let mut lines = LineBuffer::new();
+        let mut response_text = String::new();
+        let mut usage = Usage::default();
+
+        while let Some(chunk) = response.chunk().await? {
+            for line in lines.push(&chunk) {
+                let Some(data) = sse_data(&line) else {
+                    continue;
+                };
+                let event: serde_json::Value = serde_json::from_str(data)?;
+
+                if let Some(content) = event["choices"][0]["delta"]["content"].as_str() {
+                    on_token(content);
+                    response_text.push_str(content);
+                }
+                if event["usage"].is_object() {
+                    usage = Self::usage(&event["usage"]);
+                }
+            }
+        }
+
+        Ok(Completion::new(response_text, &self.model, usage))
+    }
+
+    /// Lists the models available to the API key
+    ///
+    /// # Returns
+    /// * `Ok(Some(Vec<String>))` - The identifiers of the available models
+    /// * `Err(ProviderError)` - If the API request fails after all retries
+    async fn models(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.retry
+            .run(|| async {
+                let response = self
+                    .client
+                    .get(MODELS_URL)
+                    .timeout(self.timeout)
+                    .header("Authorization", format!("Bearer {}", self.api_key))
+                    .send()
+                    .await?;
+                if !response.status().is_success() {
+                    return Err(ProviderError::from_response(response).await);
+                }
 
-        Ok(response_text)
+                let response_json: serde_json::Value = response.json().await?;
+                let models = response_json["data"]
+                    .as_array()
+                    .ok_or_else(|| {
+                        ProviderError::MalformedResponse("Failed to get model list".to_string())
+                    })?
+                    .iter()
+                    .filter_map(|model| model["id"].as_str().map(String::from))
+                    .collect();
+                Ok(Some(models))
+            })
+            .await
     }
 }
@@ -0,0 +1,150 @@
+use super::error::ProviderError;
+use crate::config::RequestConfig;
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+/// Retry strategy applied to provider requests
+///
+/// Retryable failures are retried with exponential backoff and jitter, unless the
+/// provider sent a `Retry-After` delay, which is honored instead up to the
+/// maximum backoff.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt
+    max_retries: u32,
+    /// Delay before the first retry
+    initial_backoff: Duration,
+    /// Upper bound of the computed backoff delay
+    max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Creates a retry policy from the request configuration
+    ///
+    /// # Arguments
+    /// * `config` - Timeout and retry settings
+    pub fn new(config: &RequestConfig) -> Self {
+        Self {
+            max_retries: config.max_retries,
+            initial_backoff: Duration::from_millis(config.initial_backoff_ms),
+            max_backoff: Duration::from_millis(config.max_backoff_ms),
+        }
+    }
+
+    /// Computes the jittered delay before the given retry
+    ///
+    /// # Arguments
+    /// * `attempt` - Zero-based index of the retry
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff);
+
+        // Keep half of the delay and randomize the other half
+        let half = exponential / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+
+    /// Returns the delay before retrying after an error
+    ///
+    /// # Arguments
+    /// * `error` - The retryable error of the last attempt
+    /// * `attempt` - Zero-based index of the retry
+    fn delay(&self, error: &ProviderError, attempt: u32) -> Duration {
+        match error.retry_after() {
+            Some(retry_after) => retry_after.min(self.max_backoff),
+            None => self.backoff(attempt),
+        }
+    }
+
+    /// Runs an operation, retrying it while it fails with a retryable error
+    ///
+    /// # Arguments
+    /// * `operation` - Closure starting a new attempt of the request
+    ///
+    /// # Returns
+    /// * `Ok(T)` - The result of the first successful attempt
+    /// * `Err(ProviderError)` - The last error, once retries are exhausted or not allowed
+    pub async fn run<T, F, Fut>(&self, mut operation: F) -> Result<T, ProviderError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match operation().await {
+                Err(e) if e.is_retryable() && attempt < self.max_retries => {
+                    let delay = self.delay(&e, attempt);
+                    warn!(
+                        "request failed ({}), retry {}/{} in {:?}",
+                        e,
+                        attempt + 1,
+                        self.max_retries,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::new(&RequestConfig {
+            initial_backoff_ms: 500,
+            max_backoff_ms: 4_000,
+            ..RequestConfig::default()
+        })
+    }
+
+    fn rate_limit(retry_after: Option<Duration>) -> ProviderError {
+        ProviderError::RateLimit {
+            retry_after,
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_with_jitter() {
+        let policy = policy();
+        for (attempt, full) in [(0, 500), (1, 1_000), (2, 2_000)] {
+            for _ in 0..20 {
+                let delay = policy.backoff(attempt);
+                assert!(delay >= Duration::from_millis(full / 2), "{:?}", delay);
+                assert!(delay <= Duration::from_millis(full), "{:?}", delay);
+            }
+        }
+    }
+
+    #[test]
+    fn backoff_is_capped_by_the_maximum() {
+        let policy = policy();
+        for attempt in [3, 10, 40, u32::MAX] {
+            let delay = policy.backoff(attempt);
+            assert!(delay >= Duration::from_millis(2_000), "{:?}", delay);
+            assert!(delay <= Duration::from_millis(4_000), "{:?}", delay);
+        }
+    }
+
+    #[test]
+    fn delay_honors_retry_after_up_to_the_maximum() {
+        let policy = policy();
+        assert_eq!(
+            policy.delay(&rate_limit(Some(Duration::from_secs(2))), 0),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            policy.delay(&rate_limit(Some(Duration::from_secs(3_600))), 0),
+            Duration::from_secs(4)
+        );
+        assert!(policy.delay(&rate_limit(None), 0) <= Duration::from_millis(500));
+    }
+}
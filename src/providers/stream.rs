@@ -0,0 +1,56 @@
+/// Splits a chunked HTTP body into complete lines
+///
+/// Used to decode server-sent events (OpenAI, Anthropic) and newline-delimited
+/// JSON (Ollama), where a network chunk may end in the middle of a line.
+#[derive(Default)]
+pub struct LineBuffer {
+    /// Bytes received after the last complete line
+    pending: Vec<u8>,
+}
+
+impl LineBuffer {
+    /// Creates an empty line buffer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a chunk and returns every line it completes
+    ///
+    /// # Arguments
+    /// * `chunk` - Raw bytes received from the network
+    ///
+    /// # Returns
+    /// The completed lines, without their line terminators
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(chunk);
+
+        let mut lines = Vec::new();
+        while let Some(position) = self.pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=position).collect();
+            lines.push(
+                String::from_utf8_lossy(&line)
+                    .trim_end_matches(['\r', '\n'])
+                    .to_string(),
+            );
+        }
+        lines
+    }
+
+    /// Returns the trailing line left when the body ends without a newline
+    pub fn finish(self) -> Option<String> {
+        let line = String::from_utf8_lossy(&self.pending).trim().to_string();
+        (!line.is_empty()).then_some(line)
+    }
+}
+
+/// Extracts the payload of a server-sent event `data:` line
+///
+/// # Arguments
+/// * `line` - A single line of the event stream
+///
+/// # Returns
+/// The payload, or `None` for comments, other fields and the `[DONE]` marker
+pub fn sse_data(line: &str) -> Option<&str> {
+    let data = line.strip_prefix("data:")?.trim_start();
+    (data != "[DONE]").then_some(data)
+}
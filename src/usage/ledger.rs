@@ -0,0 +1,66 @@
+use super::tracker::ModelUsage;
+use crate::config::expand_home;
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+/// A line of the usage ledger
+#[derive(Serialize)]
+struct LedgerEntry<'a> {
+    /// Time of the run, in RFC 3339 format
+    date: String,
+    /// Name of the repository the run was made in
+    repository: &'a str,
+    /// The model name
+    model: &'a str,
+    /// Tokens of the prompts sent to the model
+    prompt_tokens: u64,
+    /// Tokens generated by the model
+    completion_tokens: u64,
+    /// Cost of the tokens, `null` when the model has no configured price
+    cost: Option<f64>,
+}
+
+/// Appends the usage of a run to the ledger, one JSON line per model
+///
+/// # Arguments
+/// * `path` - Path of the ledger file, `~` is expanded
+/// * `summary` - Usage of every model used during the run
+///
+/// # Returns
+/// * `Ok(())` - If the ledger was updated
+/// * `Err(Box<dyn std::error::Error + Send + Sync>)` - If the ledger could not be written
+pub fn append_ledger(
+    path: &str,
+    summary: &[ModelUsage],
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if summary.is_empty() {
+        return Ok(());
+    }
+
+    let path = expand_home(Path::new(path))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let repository = std::env::current_dir()?
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let date = chrono::Utc::now().to_rfc3339();
+
+    let mut ledger = OpenOptions::new().create(true).append(true).open(path)?;
+    for model_usage in summary {
+        let entry = LedgerEntry {
+            date: date.clone(),
+            repository: &repository,
+            model: &model_usage.model,
+            prompt_tokens: model_usage.usage.prompt_tokens,
+            completion_tokens: model_usage.usage.completion_tokens,
+            cost: model_usage.cost,
+        };
+        writeln!(ledger, "{}", serde_json::to_string(&entry)?)?;
+    }
+    Ok(())
+}
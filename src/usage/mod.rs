@@ -0,0 +1,5 @@
+mod ledger;
+mod tracker;
+
+pub use ledger::append_ledger;
+pub use tracker::{ModelUsage, UsageTracker};
@@ -0,0 +1,224 @@
+use crate::config::ModelPrice;
+use crate::providers::{Completion, ProviderError, Usage};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+/// Tokens and cost consumed by a single model during a run
+#[derive(Clone, Debug)]
+pub struct ModelUsage {
+    /// The model name
+    pub model: String,
+    /// Tokens consumed by the model
+    pub usage: Usage,
+    /// Cost of the tokens, `None` when the model has no configured price
+    pub cost: Option<f64>,
+}
+
+/// Cost set aside for the requests sent and not answered yet
+#[derive(Default)]
+struct Reservations {
+    /// Number of requests in flight
+    pending: u32,
+    /// Estimated cost of a request, the highest cost of a single request so far
+    estimate: f64,
+}
+
+/// Accumulates token usage of a run and enforces its budget
+///
+/// Requests run concurrently, so each request reserves an estimate of its
+/// cost until it is answered, and a request is only sent when the budget
+/// covers both the cost spent and the cost reserved. The estimate is only
+/// known once a priced request has been answered, so the first requests of
+/// a run can overshoot the budget.
+pub struct UsageTracker {
+    /// Prices of the models, keyed by model name
+    pricing: HashMap<String, ModelPrice>,
+    /// Maximum cost of the run
+    budget: Option<f64>,
+    /// Tokens consumed so far, keyed by model name
+    models: Mutex<BTreeMap<String, Usage>>,
+    /// Cost reserved for the requests in flight
+    reservations: Mutex<Reservations>,
+}
+
+/// Budget reserved for a request in flight, released when dropped
+pub struct Reservation<'a> {
+    /// The tracker the budget is reserved from, `None` without budget
+    tracker: Option<&'a UsageTracker>,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if let Some(tracker) = self.tracker {
+            let mut reservations = tracker.reservations.lock().unwrap();
+            reservations.pending = reservations.pending.saturating_sub(1);
+        }
+    }
+}
+
+impl UsageTracker {
+    /// Creates an empty tracker
+    ///
+    /// # Arguments
+    /// * `pricing` - Prices of the models, keyed by model name
+    /// * `budget` - Maximum cost of the run, if any
+    pub fn new(pricing: HashMap<String, ModelPrice>, budget: Option<f64>) -> Self {
+        Self {
+            pricing,
+            budget,
+            models: Mutex::new(BTreeMap::new()),
+            reservations: Mutex::new(Reservations::default()),
+        }
+    }
+
+    /// Records the tokens consumed by a completion
+    ///
+    /// # Arguments
+    /// * `completion` - The completion returned by a provider
+    pub fn record(&self, completion: &Completion) {
+        {
+            let mut models = self.models.lock().unwrap();
+            *models.entry(completion.model.clone()).or_default() += completion.usage;
+        }
+        if let Some(cost) = self.cost(&completion.model, &completion.usage) {
+            let mut reservations = self.reservations.lock().unwrap();
+            reservations.estimate = reservations.estimate.max(cost);
+        }
+    }
+
+    /// Computes the cost of the tokens consumed by a model
+    ///
+    /// # Arguments
+    /// * `model` - The model name
+    /// * `usage` - Tokens consumed by the model
+    ///
+    /// # Returns
+    /// The cost, or `None` when the model has no configured price
+    fn cost(&self, model: &str, usage: &Usage) -> Option<f64> {
+        self.pricing.get(model).map(|price| {
+            (usage.prompt_tokens as f64 * price.input_per_million
+                + usage.completion_tokens as f64 * price.output_per_million)
+                / 1_000_000.0
+        })
+    }
+
+    /// Returns the usage of every model used during the run
+    pub fn summary(&self) -> Vec<ModelUsage> {
+        let models = self.models.lock().unwrap();
+        models
+            .iter()
+            .map(|(model, usage)| ModelUsage {
+                model: model.clone(),
+                usage: *usage,
+                cost: self.cost(model, usage),
+            })
+            .collect()
+    }
+
+    /// Returns the total cost of the priced models used during the run
+    pub fn total_cost(&self) -> f64 {
+        // Folded from zero, as summing no costs gives -0.0
+        self.summary()
+            .iter()
+            .filter_map(|model_usage| model_usage.cost)
+            .fold(0.0, |total, cost| total + cost)
+    }
+
+    /// Returns the budget of the run, if any
+    pub fn budget(&self) -> Option<f64> {
+        self.budget
+    }
+
+    /// Reserves the budget of a request about to be sent
+    ///
+    /// The reservation is held until the request is answered or fails.
+    ///
+    /// # Returns
+    /// * `Ok(Reservation)` - If there is no budget, or it covers the cost spent,
+    ///   the cost reserved by the requests in flight and this request
+    /// * `Err(ProviderError::BudgetExceeded)` - If the budget would be exceeded
+    pub fn reserve(&self) -> Result<Reservation<'_>, ProviderError> {
+        let Some(budget) = self.budget else {
+            return Ok(Reservation { tracker: None });
+        };
+
+        let mut reservations = self.reservations.lock().unwrap();
+        let spent = self.total_cost();
+        let reserved = f64::from(reservations.pending + 1) * reservations.estimate;
+        if spent >= budget || spent + reserved > budget {
+            return Err(ProviderError::BudgetExceeded { spent, budget });
+        }
+        reservations.pending += 1;
+        Ok(Reservation {
+            tracker: Some(self),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tracker pricing `gpt` at one dollar per million tokens
+    fn tracker(budget: Option<f64>) -> UsageTracker {
+        let price = ModelPrice {
+            input_per_million: 1.0,
+            output_per_million: 1.0,
+        };
+        UsageTracker::new(HashMap::from([("gpt".to_string(), price)]), budget)
+    }
+
+    /// A completion of `gpt` costing `tokens` millionths of a dollar
+    fn completion(tokens: u64) -> Completion {
+        let usage = Usage {
+            prompt_tokens: tokens,
+            completion_tokens: 0,
+        };
+        Completion::new(String::new(), "gpt", usage)
+    }
+
+    #[test]
+    fn total_cost_starts_at_positive_zero() {
+        let total = tracker(None).total_cost();
+        assert_eq!(total, 0.0);
+        assert!(total.is_sign_positive());
+    }
+
+    #[test]
+    fn reserve_is_unbounded_without_budget() {
+        let tracker = tracker(None);
+        tracker.record(&completion(10_000_000));
+        let reservations: Vec<_> = (0..10).map(|_| tracker.reserve().unwrap()).collect();
+        assert_eq!(reservations.len(), 10);
+    }
+
+    #[test]
+    fn reserve_counts_requests_in_flight() {
+        // $0.30 spent, requests estimated at $0.30, $1 of budget
+        let tracker = tracker(Some(1.0));
+        tracker.record(&completion(300_000));
+
+        let first = tracker.reserve().unwrap();
+        let second = tracker.reserve().unwrap();
+        assert!(matches!(
+            tracker.reserve(),
+            Err(ProviderError::BudgetExceeded { .. })
+        ));
+
+        // An answered or failed request releases its reservation
+        drop(first);
+        let _third = tracker.reserve().unwrap();
+        drop(second);
+    }
+
+    #[test]
+    fn reserve_fails_once_the_budget_is_spent() {
+        let tracker = tracker(Some(0.5));
+        assert!(tracker.reserve().is_ok());
+        tracker.record(&completion(500_000));
+        assert!(matches!(
+            tracker.reserve(),
+            Err(ProviderError::BudgetExceeded { .. })
+        ));
+    }
+}
@@ -1,15 +1,35 @@
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::time::Duration;
 
-/// A spinner that can be used to show progress for long-running operations
+/// Interval between two spinner animation frames
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Builds the style shared by every spinner line
+fn spinner_style() -> ProgressStyle {
+    ProgressStyle::default_spinner()
+        .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏")
+        .template("{spinner} {msg}")
+        .unwrap()
+}
+
+/// A multi-line progress view used to show progress for long-running operations
+///
+/// The view holds an optional main spinner driven by `start`/`finish`, plus one
+/// line per background task created with `add_task`.
 pub struct Spinner {
-    /// The underlying progress bar that drives the spinner animation
+    /// Container rendering every progress line together
+    multi: MultiProgress,
+    /// The underlying progress bar that drives the main spinner animation
     progress_bar: Option<ProgressBar>,
 }
 
 impl Spinner {
     /// Creates a new spinner instance
     pub fn new() -> Self {
-        Self { progress_bar: None }
+        Self {
+            multi: MultiProgress::new(),
+            progress_bar: None,
+        }
     }
 
     /// Starts the spinner with the given message
@@ -17,13 +37,9 @@ impl Spinner {
     /// # Arguments
     /// * `message` - The message to display next to the spinner
     pub fn start(&mut self, message: &str) {
-        let spinner = ProgressBar::new_spinner();
-        spinner.set_style(
-            ProgressStyle::default_spinner()
-                .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏")
-                .template("{spinner} {msg}")
-                .unwrap(),
-        );
+        let spinner = self.multi.add(ProgressBar::new_spinner());
+        spinner.set_style(spinner_style());
+        spinner.enable_steady_tick(TICK_INTERVAL);
         spinner.set_message(message.to_string());
         self.progress_bar = Some(spinner);
     }
@@ -54,6 +70,67 @@ impl Spinner {
             spinner.set_message(message.to_string());
         }
     }
+
+    /// Adds a new progress line for a task running in the background
+    ///
+    /// # Arguments
+    /// * `name` - Name of the task, shown in front of its state
+    ///
+    /// # Returns
+    /// A handle used to update the task state from any thread
+    pub fn add_task(&self, name: &str) -> TaskProgress {
+        let progress_bar = self.multi.add(ProgressBar::new_spinner());
+        progress_bar.set_style(spinner_style());
+        progress_bar.enable_steady_tick(TICK_INTERVAL);
+        let task = TaskProgress {
+            progress_bar,
+            name: name.to_string(),
+        };
+        task.set_state("queued");
+        task
+    }
+
+    /// Clears and hides every progress line until `resume` is called
+    ///
+    /// Used around interactive prompts and streamed previews so the redraws
+    /// do not interleave with other output. Tasks keep updating in the meantime.
+    pub fn pause(&self) {
+        let _ = self.multi.clear();
+        self.multi.set_draw_target(ProgressDrawTarget::hidden());
+    }
+
+    /// Shows the progress lines again after a call to `pause`
+    pub fn resume(&self) {
+        self.multi.set_draw_target(ProgressDrawTarget::stderr());
+    }
+}
+
+/// Progress line of a single task inside a `Spinner` view
+#[derive(Clone)]
+pub struct TaskProgress {
+    /// The progress bar rendering this task
+    progress_bar: ProgressBar,
+    /// Name of the task shown in front of its state
+    name: String,
+}
+
+impl TaskProgress {
+    /// Updates the current state of the task
+    ///
+    /// # Arguments
+    /// * `state` - Short description of what the task is doing
+    pub fn set_state(&self, state: &str) {
+        self.progress_bar
+            .set_message(format!("{} \x1b[2m{}\x1b[0m", self.name, state));
+    }
+
+    /// Stops the task and displays a final message
+    ///
+    /// # Arguments
+    /// * `message` - The message to display when finishing
+    pub fn finish_with_message(&self, message: &str) {
+        self.progress_bar.finish_with_message(message.to_string());
+    }
 }
 
 /// Utility functions for string manipulation
@@ -62,6 +139,8 @@ pub struct StringUtils;
 impl StringUtils {
     /// Truncates a string to the specified maximum width, adding "..." if truncated
     ///
+    /// The width is counted in characters, so multi-byte characters are never split.
+    ///
     /// # Arguments
     /// * `s` - The string to truncate
     /// * `max_width` - The maximum allowed width of the string
@@ -69,9 +148,94 @@ impl StringUtils {
     /// # Returns
     /// The truncated string with "..." appended if it was shortened
     pub fn truncate(s: &str, max_width: usize) -> String {
-        if s.len() <= max_width {
+        if s.chars().count() <= max_width {
             return s.to_string();
         }
-        format!("{}...", &s[..max_width - 3])
+        let kept: String = s.chars().take(max_width.saturating_sub(3)).collect();
+        format!("{}...", kept)
+    }
+
+    /// Splits a command line into its program and arguments
+    ///
+    /// Words are separated by whitespace, and single or double quotes group
+    /// words containing spaces. Backslashes are kept as they are, so Windows
+    /// paths need no escaping.
+    ///
+    /// # Arguments
+    /// * `command` - The command line, such as `code --wait`
+    ///
+    /// # Returns
+    /// The words of the command line, without their quotes
+    pub fn split_args(command: &str) -> Vec<String> {
+        let mut words = Vec::new();
+        let mut word: Option<String> = None;
+        let mut quote = None;
+
+        for c in command.chars() {
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => word.get_or_insert_with(String::new).push(c),
+                None if c == '"' || c == '\'' => {
+                    quote = Some(c);
+                    word.get_or_insert_with(String::new);
+                }
+                None if c.is_whitespace() => words.extend(word.take()),
+                None => word.get_or_insert_with(String::new).push(c),
+            }
+        }
+        words.extend(word);
+        words
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StringUtils;
+
+    #[test]
+    fn truncate_keeps_short_strings() {
+        assert_eq!(StringUtils::truncate("let x = 1;", 40), "let x = 1;");
+    }
+
+    #[test]
+    fn truncate_shortens_ascii() {
+        assert_eq!(StringUtils::truncate("abcdefghij", 8), "abcde...");
+    }
+
+    #[test]
+    fn truncate_splits_on_char_boundaries() {
+        let line = "let message = \"Données sauvegardées avec succès ✓ — ünïcödé\";";
+        let truncated = StringUtils::truncate(line, 40);
+        assert_eq!(truncated.chars().count(), 40);
+        assert_eq!(truncated, "let message = \"Données sauvegardées a...");
+    }
+
+    #[test]
+    fn truncate_counts_characters_not_bytes() {
+        assert_eq!(StringUtils::truncate("ééééé", 5), "ééééé");
+        assert_eq!(StringUtils::truncate("éééééé", 5), "éé...");
+    }
+
+    #[test]
+    fn split_args_separates_words() {
+        assert_eq!(StringUtils::split_args("vi"), ["vi"]);
+        assert_eq!(
+            StringUtils::split_args("  code   --wait "),
+            ["code", "--wait"]
+        );
+        assert!(StringUtils::split_args("   ").is_empty());
+    }
+
+    #[test]
+    fn split_args_groups_quoted_words() {
+        assert_eq!(
+            StringUtils::split_args(r#""C:\Program Files\Notepad++\notepad++.exe" -multiInst"#),
+            [r"C:\Program Files\Notepad++\notepad++.exe", "-multiInst"]
+        );
+        assert_eq!(
+            StringUtils::split_args("emacs -nw --eval '(setq x 1)'"),
+            ["emacs", "-nw", "--eval", "(setq x 1)"]
+        );
+        assert_eq!(StringUtils::split_args("vim ''"), ["vim", ""]);
     }
 }
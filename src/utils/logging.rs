@@ -6,16 +6,14 @@ use tracing_appender::rolling::RollingFileAppender;
 pub struct Logger;
 
 impl Logger {
-    /// Sets up file-based logging for the application
+    /// Returns the directory log files are written to
     ///
-    /// Creates a log file in the user's home directory under `.cllm/cllm.log`
     /// Uses the appropriate home directory path for Windows vs Unix systems
     ///
     /// # Returns
-    /// * `Ok(())` - If logging was successfully configured
-    /// * `Err(UtilsError)` - If there was an error setting up logging
-    pub fn setup() -> Result<(), UtilsError> {
-        let log_file = "cllm.log";
+    /// * `Ok(PathBuf)` - The `.cllm` directory of the user's home directory
+    /// * `Err(UtilsError)` - If the home directory is not set
+    pub fn directory() -> Result<PathBuf, UtilsError> {
         let home = if cfg!(windows) {
             std::env::var("USERPROFILE").map_err(|_| {
                 UtilsError::LoggingError("USERPROFILE environment variable not set".to_string())
@@ -25,8 +23,19 @@ impl Logger {
                 UtilsError::LoggingError("HOME environment variable not set".to_string())
             })?
         };
+        Ok(PathBuf::from(home).join(".cllm"))
+    }
 
-        let directory = PathBuf::from(home).join(".cllm");
+    /// Sets up file-based logging for the application
+    ///
+    /// Creates a log file in the user's home directory under `.cllm/cllm.log`
+    ///
+    /// # Returns
+    /// * `Ok(())` - If logging was successfully configured
+    /// * `Err(UtilsError)` - If there was an error setting up logging
+    pub fn setup() -> Result<(), UtilsError> {
+        let log_file = "cllm.log";
+        let directory = Self::directory()?;
         let file_appender = RollingFileAppender::builder()
             .rotation(tracing_appender::rolling::Rotation::NEVER)
             .filename_prefix(log_file)
@@ -2,5 +2,5 @@ mod display;
 mod error;
 mod logging;
 
-pub use display::Spinner;
+pub use display::{Spinner, StringUtils, TaskProgress};
 pub use logging::Logger;